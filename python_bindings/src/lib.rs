@@ -1,4 +1,7 @@
+#![allow(non_local_definitions)] // pyo3 0.20 macros expand impls inside functions
+
 use pyo3::prelude::*;
+use rust_sim::simulator::ShotIterator;
 use rust_sim::QuantumSimulator as RustSimulator;
 
 /// Python-exposed quantum simulator class
@@ -16,6 +19,11 @@ impl PyQuantumSimulator {
         }
     }
 
+    /// Seed the random number generator used for sampling
+    fn set_seed(&mut self, seed: u64) {
+        self.inner.set_seed(seed);
+    }
+
     /// Reset to |0...0⟩ state
     fn reset(&mut self) {
         self.inner.reset();
@@ -30,7 +38,7 @@ impl PyQuantumSimulator {
     ) -> PyResult<()> {
         self.inner
             .apply_gate(gate_name, &wires, &params)
-            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e))
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
     }
 
     /// Apply idle noise to a qubit
@@ -63,11 +71,52 @@ impl PyQuantumSimulator {
         Ok(self.inner.measure_shots(n_shots))
     }
 
+    /// Iterate over N shots as bitstrings (wire 0 first) without building a list
+    fn iter_shots(&self, n_shots: usize) -> PyShotIterator {
+        PyShotIterator {
+            inner: self.inner.shot_iterator(n_shots),
+        }
+    }
+
     /// Get probability distribution from density matrix diagonal
     fn probabilities(&self) -> PyResult<Vec<f64>> {
         Ok(self.inner.get_state().probabilities())
     }
 
+    /// Expectation value of a Pauli string such as "ZZ" or "XIZ" acting on the given wires
+    /// (character i acts on wires[i], identity elsewhere)
+    fn expectation_pauli(&self, pauli_string: &str, wires: Vec<usize>) -> PyResult<f64> {
+        let ops: Vec<char> = pauli_string.chars().collect();
+        if ops.len() != wires.len() {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Pauli string '{}' has {} operator(s) but {} wire(s) were given",
+                pauli_string,
+                ops.len(),
+                wires.len()
+            )));
+        }
+        let num_qubits = self.inner.get_state().num_qubits;
+        let mut full = vec!['I'; num_qubits];
+        for (i, (&op, &wire)) in ops.iter().zip(&wires).enumerate() {
+            if wire >= num_qubits {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Wire {} out of range",
+                    wire
+                )));
+            }
+            if wires[..i].contains(&wire) {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Wire {} appears more than once",
+                    wire
+                )));
+            }
+            full[wire] = op;
+        }
+        self.inner
+            .expectation_pauli(&full.into_iter().collect::<String>())
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
     /// Get trace and purity
     fn get_metrics(&self) -> PyResult<(f64, f64)> {
         Ok(self.inner.get_metrics())
@@ -85,9 +134,29 @@ impl PyQuantumSimulator {
     }
 }
 
+/// Python iterator over lazily sampled measurement shots
+#[pyclass(name = "ShotIterator")]
+pub struct PyShotIterator {
+    inner: ShotIterator,
+}
+
+#[pymethods]
+impl PyShotIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>) -> Option<String> {
+        slf.inner
+            .next()
+            .map(|bits| bits.iter().map(|&b| if b == 1 { '1' } else { '0' }).collect())
+    }
+}
+
 /// Python module definition
 #[pymodule]
 fn quantum_core(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<PyQuantumSimulator>()?;
+    m.add_class::<PyShotIterator>()?;
     Ok(())
 }
@@ -0,0 +1,789 @@
+use nalgebra::DMatrix;
+use num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use crate::error::QsimError;
+use crate::gates::{single_qubit_gate, u3, u3_angles};
+use crate::simulator::QuantumSimulator;
+
+/// Name of a gate instruction that carries its own unitary in `GateOp::matrix`
+pub const CUSTOM_MATRIX_GATE: &str = "Matrix";
+
+/// A single gate instruction, named as accepted by `QuantumSimulator::apply_gate`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GateOp {
+    #[serde(alias = "gate")]
+    pub name: String,
+    pub wires: Vec<usize>,
+    pub params: Vec<f64>,
+    /// Row-major [re, im] entries of the 2ᵏ×2ᵏ unitary of a "Matrix" op on k wires
+    /// (wires[0] is its most significant bit); `None` for named gates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<Vec<[f64; 2]>>,
+}
+
+impl GateOp {
+    pub fn new(name: &str, wires: &[usize], params: &[f64]) -> Self {
+        GateOp {
+            name: name.to_string(),
+            wires: wires.to_vec(),
+            params: params.to_vec(),
+            matrix: None,
+        }
+    }
+
+    /// A custom-matrix instruction applying `unitary` to `wires`
+    pub fn custom(unitary: &DMatrix<Complex<f64>>, wires: &[usize]) -> Self {
+        // DMatrix is column-major; store row by row so the JSON reads naturally
+        let entries = unitary.transpose().iter().map(|z| [z.re, z.im]).collect();
+        GateOp {
+            name: CUSTOM_MATRIX_GATE.to_string(),
+            wires: wires.to_vec(),
+            params: Vec::new(),
+            matrix: Some(entries),
+        }
+    }
+
+    /// The unitary carried by a custom-matrix instruction, if any
+    pub fn custom_matrix(&self) -> Option<DMatrix<Complex<f64>>> {
+        let entries = self.matrix.as_ref()?;
+        let dim = 1 << self.wires.len();
+        let values: Vec<Complex<f64>> = entries.iter().map(|&[re, im]| Complex::new(re, im)).collect();
+        (values.len() == dim * dim).then(|| DMatrix::from_row_slice(dim, dim, &values))
+    }
+
+    /// The gate that undoes this one: rotation angles are negated, S/T map to S†/T†
+    /// (and back), U3(θ, φ, λ) maps to U3(-θ, -λ, -φ), custom matrices are replaced by
+    /// their adjoint, and self-inverse gates are returned unchanged
+    pub fn inverse(&self) -> GateOp {
+        if let Some(unitary) = self.custom_matrix() {
+            return GateOp::custom(&unitary.adjoint(), &self.wires);
+        }
+        let name = match self.name.as_str() {
+            "S" => "Sdg",
+            "Sdg" => "S",
+            "T" => "Tdg",
+            "Tdg" => "T",
+            other => other,
+        };
+        let params = match self.name.as_str() {
+            "RX" | "RY" | "RZ" | "RXX" | "RYY" | "RZZ" | "CRX" | "CRY" | "CRZ" | "CPhase" | "CP"
+            | "CPhaseFraction" | "XYZ" | "Canonical" | "GlobalPhase" => {
+                self.params.iter().map(|p| -p).collect()
+            }
+            "U3" if self.params.len() == 3 => {
+                vec![-self.params[0], -self.params[2], -self.params[1]]
+            }
+            _ => self.params.clone(),
+        };
+
+        GateOp {
+            name: name.to_string(),
+            wires: self.wires.clone(),
+            params,
+            matrix: self.matrix.clone(),
+        }
+    }
+}
+
+/// Accumulated product of a run of single-qubit gates on one wire, with the gates themselves
+type PendingRun = Option<(DMatrix<Complex<f64>>, Vec<GateOp>)>;
+
+/// An ordered sequence of gate instructions
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Circuit {
+    pub ops: Vec<GateOp>,
+}
+
+impl Circuit {
+    /// Create an empty circuit
+    pub fn new() -> Self {
+        Circuit { ops: Vec::new() }
+    }
+
+    /// Append a gate to the end of the circuit
+    pub fn add_gate(&mut self, name: &str, wires: &[usize], params: &[f64]) -> &mut Self {
+        self.ops.push(GateOp::new(name, wires, params));
+        self
+    }
+
+    /// Append a custom unitary on `wires` (see `GateOp::custom`)
+    pub fn add_matrix(&mut self, unitary: &DMatrix<Complex<f64>>, wires: &[usize]) -> &mut Self {
+        self.ops.push(GateOp::custom(unitary, wires));
+        self
+    }
+
+    /// Quantum Fourier transform on `wires` (wires[0] is the most significant qubit)
+    /// The final qubit-reversal swaps are omitted, so the output register is bit-reversed
+    pub fn qft(wires: &[usize]) -> Circuit {
+        Circuit::approximate_qft(wires, wires.len())
+    }
+
+    /// QFT keeping only controlled-phase terms 2π/2^k with k ≤ `max_k`
+    /// Smaller `max_k` drops the smallest rotations, trading accuracy for gate count
+    pub fn approximate_qft(wires: &[usize], max_k: usize) -> Circuit {
+        let mut circuit = Circuit::new();
+        for (j, &target) in wires.iter().enumerate() {
+            circuit.add_gate("Hadamard", &[target], &[]);
+            for (m, &control) in wires.iter().enumerate().skip(j + 1) {
+                let k = m - j + 1;
+                if k <= max_k {
+                    circuit.add_gate("CPhaseFraction", &[control, target], &[k as f64]);
+                }
+            }
+        }
+        circuit
+    }
+
+    /// First-order Trotter circuit for exp(-i H t) with H = Σ cⱼ Pⱼ given as Pauli strings
+    /// (character i acts on wire i). Each step applies exp(-i cⱼ Pⱼ t / steps) for every term
+    /// in order, as a rotation of angle 2 cⱼ t / steps: RX/RY/RZ for single-qubit terms,
+    /// RXX/RYY/RZZ for XX/YY/ZZ pairs, and a basis change plus CNOT ladder around an RZ
+    /// otherwise. Identity terms only contribute a global phase and are skipped.
+    pub fn trotter(hamiltonian: &[(f64, String)], time: f64, steps: usize) -> Result<Circuit, QsimError> {
+        if steps == 0 {
+            return Err(QsimError::InvalidParameter(
+                "Trotter decomposition needs at least one step".to_string(),
+            ));
+        }
+        for (_, pauli) in hamiltonian {
+            if let Some(op) = pauli.chars().find(|c| !"IXYZ".contains(*c)) {
+                return Err(QsimError::InvalidParameter(format!(
+                    "Invalid Pauli operator '{}' in '{}'",
+                    op,
+                    pauli
+                )));
+            }
+        }
+
+        let mut circuit = Circuit::new();
+        for _ in 0..steps {
+            for (coeff, pauli) in hamiltonian {
+                let angle = 2.0 * coeff * time / steps as f64;
+                circuit.add_pauli_rotation(pauli, angle);
+            }
+        }
+        Ok(circuit)
+    }
+
+    /// Append exp(-i θ/2 P) for a validated Pauli string P
+    fn add_pauli_rotation(&mut self, pauli: &str, angle: f64) {
+        let support: Vec<(usize, char)> = pauli.chars().enumerate().filter(|&(_, c)| c != 'I').collect();
+
+        match support.as_slice() {
+            [] => {}
+            [(w, op)] => {
+                self.add_gate(&format!("R{}", op), &[*w], &[angle]);
+            }
+            [(a, op_a), (b, op_b)] if op_a == op_b => {
+                self.add_gate(&format!("R{}{}", op_a, op_b), &[*a, *b], &[angle]);
+            }
+            _ => {
+                // Rotate every factor onto Z, collect the parity on the last wire, rotate it,
+                // then undo the ladder and the basis change
+                let half_pi = std::f64::consts::FRAC_PI_2;
+                for &(w, op) in &support {
+                    match op {
+                        'X' => {
+                            self.add_gate("Hadamard", &[w], &[]);
+                        }
+                        'Y' => {
+                            self.add_gate("RX", &[w], &[half_pi]);
+                        }
+                        _ => {}
+                    }
+                }
+                for pair in support.windows(2) {
+                    self.add_gate("CNOT", &[pair[0].0, pair[1].0], &[]);
+                }
+                let last = support[support.len() - 1].0;
+                self.add_gate("RZ", &[last], &[angle]);
+                for pair in support.windows(2).rev() {
+                    self.add_gate("CNOT", &[pair[0].0, pair[1].0], &[]);
+                }
+                for &(w, op) in &support {
+                    match op {
+                        'X' => {
+                            self.add_gate("Hadamard", &[w], &[]);
+                        }
+                        'Y' => {
+                            self.add_gate("RX", &[w], &[-half_pi]);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Circuit that undoes this one: every gate inverted, in reverse order
+    pub fn inverse(&self) -> Circuit {
+        Circuit {
+            ops: self.ops.iter().rev().map(GateOp::inverse).collect(),
+        }
+    }
+
+    /// Merge each run of consecutive single-qubit gates on a wire into one U3 gate
+    ///
+    /// Runs are broken by any multi-qubit or unrecognized gate touching the wire. The
+    /// product of a run is converted back to U3 angles; a run of one gate is kept as written.
+    /// The run's global phase is kept as a trailing GlobalPhase gate, so the fused circuit
+    /// stays exact when used under control (`QuantumSimulator::apply_controlled_circuit`).
+    pub fn fuse(&self) -> Circuit {
+        let mut fused = Circuit::new();
+        let mut pending: Vec<PendingRun> = vec![None; self.num_qubits()];
+
+        fn flush(slot: &mut PendingRun, out: &mut Circuit) {
+            match slot.take() {
+                Some((_, mut run)) if run.len() == 1 => out.ops.append(&mut run),
+                Some((product, run)) => {
+                    let (theta, phi, lambda) = u3_angles(&product);
+                    out.add_gate("U3", &run[0].wires, &[theta, phi, lambda]);
+                    // product = e^{iα} U3; read α off the largest entry of U3
+                    let u3_matrix = u3(theta, phi, lambda);
+                    let (k, _) = u3_matrix
+                        .iter()
+                        .enumerate()
+                        .max_by(|a, b| a.1.norm().total_cmp(&b.1.norm()))
+                        .expect("2x2 matrix has entries");
+                    let alpha = (product.as_slice()[k] / u3_matrix.as_slice()[k]).arg();
+                    if alpha.abs() > 1e-12 {
+                        out.add_gate("GlobalPhase", &run[0].wires, &[alpha]);
+                    }
+                }
+                None => {}
+            }
+        }
+
+        for op in &self.ops {
+            let matrix = match op.wires.as_slice() {
+                [_] => single_qubit_gate(&op.name, &op.params),
+                _ => None,
+            };
+            match matrix {
+                Some(matrix) => {
+                    let slot = &mut pending[op.wires[0]];
+                    match slot {
+                        Some((product, run)) => {
+                            *product = &matrix * &*product;
+                            run.push(op.clone());
+                        }
+                        None => *slot = Some((matrix, vec![op.clone()])),
+                    }
+                }
+                None => {
+                    for &w in &op.wires {
+                        flush(&mut pending[w], &mut fused);
+                    }
+                    fused.ops.push(op.clone());
+                }
+            }
+        }
+        for slot in pending.iter_mut() {
+            flush(slot, &mut fused);
+        }
+
+        fused
+    }
+
+    /// Number of gate instructions
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Check whether the circuit has no gates
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Smallest register size that holds every wire the circuit touches
+    pub fn num_qubits(&self) -> usize {
+        self.ops
+            .iter()
+            .flat_map(|op| op.wires.iter())
+            .map(|&w| w + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Serialize the gate list to JSON: [{"name": "H", "wires": [0], "params": []}, ...]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.ops).expect("gate list is always serializable")
+    }
+
+    /// Load a circuit from the JSON gate list produced by `to_json`
+    /// Each gate object may name its gate under "name" or "gate"; names are matched
+    /// case-insensitively (so "cx" loads as CNOT) and wire and parameter counts are checked.
+    /// A "Matrix" op carries its unitary as row-major [re, im] pairs under "matrix".
+    pub fn from_json(s: &str) -> Result<Circuit, QsimError> {
+        let mut ops: Vec<GateOp> = serde_json::from_str(s)
+            .map_err(|e| QsimError::InvalidParameter(format!("Invalid circuit JSON: {}", e)))?;
+        for (idx, op) in ops.iter_mut().enumerate() {
+            op.name = validate_gate(op).map_err(|e| match e {
+                QsimError::InvalidParameter(msg) => {
+                    QsimError::InvalidParameter(format!("Gate {}: {}", idx, msg))
+                }
+                other => other,
+            })?;
+        }
+        Ok(Circuit { ops })
+    }
+
+    /// Exact output probabilities of the circuit on a fresh noiseless register of `num_qubits`
+    pub fn ideal_distribution(&self, num_qubits: usize) -> Result<Vec<f64>, QsimError> {
+        let mut sim = QuantumSimulator::new(num_qubits);
+        sim.run_circuit(self)?;
+        Ok(sim.get_state().probabilities())
+    }
+
+    /// Group gates into layers of mutually disjoint wires (as-soon-as-possible scheduling)
+    /// Each layer holds indices into `ops`; gate order on any single wire is preserved
+    pub fn layers(&self) -> Vec<Vec<usize>> {
+        let mut layers: Vec<Vec<usize>> = Vec::new();
+        let mut wire_depth = vec![0usize; self.num_qubits()];
+
+        for (idx, op) in self.ops.iter().enumerate() {
+            let layer = op.wires.iter().map(|&w| wire_depth[w]).max().unwrap_or(0);
+            if layer == layers.len() {
+                layers.push(Vec::new());
+            }
+            layers[layer].push(idx);
+            for &w in &op.wires {
+                wire_depth[w] = layer + 1;
+            }
+        }
+
+        layers
+    }
+
+    /// Circuit depth (number of layers)
+    pub fn depth(&self) -> usize {
+        self.layers().len()
+    }
+}
+
+/// Gates `QuantumSimulator::apply_gate` understands: accepted names (the first is canonical),
+/// number of wires and number of parameters
+const GATE_SIGNATURES: &[(&[&str], usize, usize)] = &[
+    (&["PauliX", "X"], 1, 0),
+    (&["PauliY", "Y"], 1, 0),
+    (&["PauliZ", "Z"], 1, 0),
+    (&["Hadamard", "H"], 1, 0),
+    (&["S"], 1, 0),
+    (&["Sdg"], 1, 0),
+    (&["T"], 1, 0),
+    (&["Tdg"], 1, 0),
+    (&["RX"], 1, 1),
+    (&["RY"], 1, 1),
+    (&["RZ"], 1, 1),
+    (&["U3"], 1, 3),
+    (&["GlobalPhase"], 1, 1),
+    (&["CNOT", "CX"], 2, 0),
+    (&["SWAP"], 2, 0),
+    (&["CZ"], 2, 0),
+    (&["CY"], 2, 0),
+    (&["CH"], 2, 0),
+    (&["CRX"], 2, 1),
+    (&["CRY"], 2, 1),
+    (&["CRZ"], 2, 1),
+    (&["CPhase", "CP"], 2, 1),
+    (&["Toffoli", "CCX"], 3, 0),
+    (&["RXX"], 2, 1),
+    (&["RYY"], 2, 1),
+    (&["RZZ"], 2, 1),
+    (&["XYZ", "Canonical"], 2, 3),
+    (&["CPhaseFraction"], 2, 1),
+];
+
+/// Check a parsed gate against `GATE_SIGNATURES`, returning the name to store
+/// Exact matches keep their spelling; case-insensitive matches map to the canonical name
+fn validate_gate(op: &GateOp) -> Result<String, QsimError> {
+    if op.matrix.is_some() || op.name.eq_ignore_ascii_case(CUSTOM_MATRIX_GATE) {
+        return validate_custom_matrix(op);
+    }
+    let (stored, n_wires, n_params) = GATE_SIGNATURES
+        .iter()
+        .find_map(|&(names, w, p)| names.contains(&op.name.as_str()).then(|| (op.name.clone(), w, p)))
+        .or_else(|| {
+            GATE_SIGNATURES.iter().find_map(|&(names, w, p)| {
+                names
+                    .iter()
+                    .any(|n| n.eq_ignore_ascii_case(&op.name))
+                    .then(|| (names[0].to_string(), w, p))
+            })
+        })
+        .ok_or_else(|| QsimError::UnknownGate(op.name.clone()))?;
+
+    if op.wires.len() != n_wires {
+        return Err(QsimError::InvalidParameter(format!(
+            "{} takes {} wire(s), got {}",
+            stored,
+            n_wires,
+            op.wires.len()
+        )));
+    }
+    // SWAP on a single wire is the identity, matching `QuantumSimulator::apply_gate`
+    let repeats_wire = op.wires.iter().enumerate().any(|(i, w)| op.wires[..i].contains(w));
+    if repeats_wire && stored != "SWAP" {
+        return Err(QsimError::InvalidParameter(format!("{} wires must be distinct", stored)));
+    }
+    if op.params.len() != n_params {
+        return Err(QsimError::InvalidParameter(format!(
+            "{} takes {} parameter(s), got {}",
+            stored,
+            n_params,
+            op.params.len()
+        )));
+    }
+    Ok(stored)
+}
+
+/// Check a custom-matrix op: a non-empty set of distinct wires, no parameters and a
+/// 2ᵏ×2ᵏ matrix for k wires (unitarity is checked when the op is applied)
+fn validate_custom_matrix(op: &GateOp) -> Result<String, QsimError> {
+    if !op.name.eq_ignore_ascii_case(CUSTOM_MATRIX_GATE) {
+        return Err(QsimError::InvalidParameter(format!(
+            "Only {} ops may carry a matrix, got one on {}",
+            CUSTOM_MATRIX_GATE, op.name
+        )));
+    }
+    let entries = op.matrix.as_ref().ok_or_else(|| {
+        QsimError::InvalidParameter(format!("{} op has no matrix", CUSTOM_MATRIX_GATE))
+    })?;
+    if op.wires.is_empty() || op.wires.iter().enumerate().any(|(i, w)| op.wires[..i].contains(w)) {
+        return Err(QsimError::InvalidParameter(format!(
+            "{} needs at least one wire, all distinct",
+            CUSTOM_MATRIX_GATE
+        )));
+    }
+    if !op.params.is_empty() {
+        return Err(QsimError::InvalidParameter(format!(
+            "{} takes no parameters, got {}",
+            CUSTOM_MATRIX_GATE,
+            op.params.len()
+        )));
+    }
+    let dim = 1usize << op.wires.len();
+    if entries.len() != dim * dim {
+        return Err(QsimError::InvalidParameter(format!(
+            "{} on {} wire(s) needs {} entries, got {}",
+            CUSTOM_MATRIX_GATE,
+            op.wires.len(),
+            dim * dim,
+            entries.len()
+        )));
+    }
+    Ok(CUSTOM_MATRIX_GATE.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_layers_respect_wire_order() {
+        let mut circuit = Circuit::new();
+        circuit
+            .add_gate("H", &[0], &[])
+            .add_gate("H", &[1], &[])
+            .add_gate("CNOT", &[0, 1], &[])
+            .add_gate("RX", &[2], &[0.1])
+            .add_gate("RZ", &[1], &[0.2]);
+
+        assert_eq!(circuit.num_qubits(), 3);
+        assert_eq!(circuit.layers(), vec![vec![0, 1, 3], vec![2], vec![4]]);
+        assert_eq!(circuit.depth(), 3);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        use crate::simulator::QuantumSimulator;
+
+        let mut circuit = Circuit::new();
+        circuit
+            .add_gate("Hadamard", &[0], &[])
+            .add_gate("CNOT", &[0, 1], &[])
+            .add_gate("RX", &[1], &[0.123_456_789_012_345_6])
+            .add_gate("RZZ", &[1, 0], &[-2.0 / 3.0]);
+
+        let json = circuit.to_json();
+        let loaded = Circuit::from_json(&json).unwrap();
+        assert_eq!(loaded, circuit);
+
+        let mut original = QuantumSimulator::new(2);
+        original.run_circuit(&circuit).unwrap();
+        let mut reloaded = QuantumSimulator::new(2);
+        reloaded.run_circuit(&loaded).unwrap();
+        assert_eq!(original.get_state().matrix, reloaded.get_state().matrix);
+
+        assert!(Circuit::from_json("[{\"name\": \"H\"}]").is_err());
+    }
+
+    #[test]
+    fn test_json_round_trip_keeps_swap_on_one_wire() {
+        use crate::simulator::QuantumSimulator;
+
+        let mut circuit = Circuit::new();
+        circuit.add_gate("Hadamard", &[0], &[]).add_gate("SWAP", &[1, 1], &[]);
+        let mut sim = QuantumSimulator::new(2);
+        sim.run_circuit(&circuit).unwrap();
+
+        let loaded = Circuit::from_json(&circuit.to_json()).unwrap();
+        assert_eq!(loaded, circuit);
+    }
+
+    #[test]
+    fn test_from_json_accepts_gate_objects() {
+        let json = r#"[
+            {"gate": "h", "wires": [0], "params": []},
+            {"gate": "cx", "wires": [0, 1], "params": []},
+            {"gate": "RY", "wires": [2], "params": [0.25]},
+            {"gate": "rzz", "wires": [1, 2], "params": [-1.5]}
+        ]"#;
+        let circuit = Circuit::from_json(json).unwrap();
+        let names: Vec<&str> = circuit.ops.iter().map(|op| op.name.as_str()).collect();
+        assert_eq!(names, ["Hadamard", "CNOT", "RY", "RZZ"]);
+        assert_eq!(circuit.ops[3].params, vec![-1.5]);
+        assert_eq!(Circuit::from_json(&circuit.to_json()).unwrap(), circuit);
+
+        let bad = [
+            r#"[{"gate": "foo", "wires": [0], "params": []}]"#,
+            r#"[{"gate": "cx", "wires": [0], "params": []}]"#,
+            r#"[{"gate": "cx", "wires": [1, 1], "params": []}]"#,
+            r#"[{"gate": "rx", "wires": [0], "params": []}]"#,
+        ];
+        for json in bad {
+            assert!(Circuit::from_json(json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_inverse_circuit_uncomputes() {
+        use crate::simulator::QuantumSimulator;
+
+        assert_eq!(GateOp::new("S", &[0], &[]).inverse(), GateOp::new("Sdg", &[0], &[]));
+        assert_eq!(GateOp::new("Tdg", &[1], &[]).inverse(), GateOp::new("T", &[1], &[]));
+        assert_eq!(GateOp::new("RZZ", &[0, 1], &[0.4]).inverse().params, vec![-0.4]);
+
+        let mut circuit = Circuit::qft(&[0, 1, 2]);
+        circuit
+            .add_gate("RX", &[0], &[0.3])
+            .add_gate("CNOT", &[2, 1], &[])
+            .add_gate("RYY", &[0, 2], &[1.1])
+            .add_gate("PauliY", &[1], &[]);
+
+        let mut sim = QuantumSimulator::new(3);
+        sim.apply_gate("RY", &[1], &[0.6]).unwrap();
+        let start = sim.get_state().clone();
+        sim.run_circuit(&circuit).unwrap();
+        assert!(start.trace_distance(sim.get_state()).unwrap() > 0.1);
+        sim.run_circuit(&circuit.inverse()).unwrap();
+        assert!(start.trace_distance(sim.get_state()).unwrap() < 1e-10);
+    }
+
+    #[test]
+    fn test_trotter_matches_direct_evolution() {
+        use crate::gates::{kron, pauli_x, pauli_y, pauli_z};
+        use crate::simulator::QuantumSimulator;
+        use nalgebra::DMatrix;
+        use num_complex::Complex;
+
+        // Single Z term: four Trotter steps equal one RZ(2 c t)
+        let circuit = Circuit::trotter(&[(0.7, "Z".to_string())], 1.3, 4).unwrap();
+        assert_eq!(circuit.len(), 4);
+        let mut trotter = QuantumSimulator::new(1);
+        trotter.apply_gate("Hadamard", &[0], &[]).unwrap();
+        let mut direct = QuantumSimulator::new(1);
+        direct.apply_gate("Hadamard", &[0], &[]).unwrap();
+        trotter.run_circuit(&circuit).unwrap();
+        direct.apply_gate("RZ", &[0], &[2.0 * 0.7 * 1.3]).unwrap();
+        assert!(trotter.get_state().trace_distance(direct.get_state()).unwrap() < 1e-12);
+
+        // A mixed three-qubit term goes through the CNOT ladder and is still exact
+        let circuit = Circuit::trotter(&[(0.4, "XZY".to_string())], 0.9, 1).unwrap();
+        let mut sim = QuantumSimulator::new(3);
+        sim.apply_gate("RY", &[0], &[0.5]).unwrap();
+        sim.apply_gate("Hadamard", &[1], &[]).unwrap();
+        let mut expected = sim.get_state().clone();
+        sim.run_circuit(&circuit).unwrap();
+
+        let pauli = kron(&kron(&pauli_x(), &pauli_z()), &pauli_y());
+        let unitary: DMatrix<Complex<f64>> = (pauli * Complex::new(0.0, -0.4 * 0.9)).exp();
+        expected.apply_unitary(&unitary);
+        assert!(sim.get_state().trace_distance(&expected).unwrap() < 1e-10);
+
+        assert!(Circuit::trotter(&[(1.0, "ZQ".to_string())], 1.0, 1).is_err());
+        assert!(Circuit::trotter(&[(1.0, "Z".to_string())], 1.0, 0).is_err());
+    }
+
+    #[test]
+    fn test_fuse_merges_single_qubit_runs() {
+        use crate::simulator::QuantumSimulator;
+
+        let mut circuit = Circuit::new();
+        circuit
+            .add_gate("H", &[0], &[])
+            .add_gate("RZ", &[0], &[0.3])
+            .add_gate("RX", &[0], &[1.1])
+            .add_gate("RY", &[1], &[0.7])
+            .add_gate("PauliX", &[1], &[])
+            .add_gate("CNOT", &[0, 1], &[])
+            .add_gate("RY", &[0], &[-0.4])
+            .add_gate("RZ", &[1], &[2.0])
+            .add_gate("Hadamard", &[1], &[])
+            .add_gate("PauliY", &[1], &[])
+            .add_gate("RX", &[2], &[0.5]);
+
+        let fused = circuit.fuse();
+        let gates = fused.ops.iter().filter(|op| op.name != "GlobalPhase").count();
+        assert_eq!(gates, 6);
+        assert!(fused.len() < circuit.len());
+
+        let mut original = QuantumSimulator::new(3);
+        original.run_circuit(&circuit).unwrap();
+        let mut merged = QuantumSimulator::new(3);
+        merged.run_circuit(&fused).unwrap();
+        let diff = (&original.get_state().matrix - &merged.get_state().matrix).norm();
+        assert!(diff < 1e-12, "fused state differs by {}", diff);
+
+        // Round trip through the inverse as well, covering U3 inversion
+        merged.run_circuit(&fused.inverse()).unwrap();
+        assert!((merged.get_state().matrix[(0, 0)].re - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_u3_angles_recover_special_cases() {
+        use crate::gates::{hadamard, pauli_x, pauli_z, u3};
+
+        for gate in [hadamard(), pauli_x(), pauli_z(), u3(0.4, -1.2, 2.5)] {
+            let (theta, phi, lambda) = u3_angles(&gate);
+            let rebuilt = u3(theta, phi, lambda);
+            // Equal up to global phase: |Tr(A†B)| = 2
+            let overlap = (gate.adjoint() * rebuilt).trace().norm();
+            assert!((overlap - 2.0).abs() < 1e-12, "{}", overlap);
+        }
+    }
+
+    #[test]
+    fn test_ideal_distribution_of_bell_circuit() {
+        let mut bell = Circuit::new();
+        bell.add_gate("Hadamard", &[0], &[]).add_gate("CNOT", &[0, 1], &[]);
+        let probs = bell.ideal_distribution(2).unwrap();
+        for (p, expected) in probs.iter().zip([0.5, 0.0, 0.0, 0.5]) {
+            assert_relative_eq!(*p, expected, epsilon = 1e-12);
+        }
+        assert_eq!(bell.ideal_distribution(1), Err(QsimError::WireOutOfRange(1)));
+    }
+
+    #[test]
+    fn test_json_round_trip_with_custom_matrix() {
+        use crate::gates::{build_swap_unitary, u3};
+
+        // i·SWAP on reversed wires and an arbitrary single-qubit unitary
+        let swap = build_swap_unitary(0, 1, 2);
+        let phased = &swap * Complex::new(0.0, 1.0);
+        let mut circuit = Circuit::new();
+        circuit
+            .add_gate("Hadamard", &[0], &[])
+            .add_matrix(&u3(0.7, -0.4, 1.9), &[2])
+            .add_matrix(&phased, &[2, 0])
+            .add_gate("CNOT", &[0, 1], &[]);
+
+        let json = circuit.to_json();
+        assert!(json.contains("\"matrix\""));
+        assert!(!Circuit::new().add_gate("H", &[0], &[]).to_json().contains("matrix"));
+        let loaded = Circuit::from_json(&json).unwrap();
+        assert_eq!(loaded, circuit);
+        assert_eq!(loaded.ops[1].custom_matrix().unwrap(), u3(0.7, -0.4, 1.9));
+
+        let mut original = QuantumSimulator::new(3);
+        original.run_circuit(&circuit).unwrap();
+        let mut reloaded = QuantumSimulator::new(3);
+        reloaded.run_circuit(&loaded).unwrap();
+        assert_eq!(original.get_state().matrix, reloaded.get_state().matrix);
+
+        // The custom op acts like the same unitary applied directly
+        let mut direct = QuantumSimulator::new(3);
+        direct.apply_gate("Hadamard", &[0], &[]).unwrap();
+        direct.apply_controlled_matrix(&u3(0.7, -0.4, 1.9), &[], &[2]).unwrap();
+        direct.apply_controlled_matrix(&phased, &[], &[2, 0]).unwrap();
+        direct.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        let diff = &direct.get_state().matrix - &original.get_state().matrix;
+        assert_relative_eq!(diff.norm(), 0.0, epsilon = 1e-12);
+
+        // Running the inverse undoes the circuit
+        original.run_circuit(&circuit.inverse()).unwrap();
+        assert_relative_eq!(original.get_state().probabilities()[0], 1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_from_json_validates_custom_matrix() {
+        let bad = [
+            r#"[{"name": "Matrix", "wires": [0], "params": []}]"#,
+            r#"[{"name": "Matrix", "wires": [0], "params": [], "matrix": [[1, 0], [0, 0], [0, 0]]}]"#,
+            r#"[{"name": "Matrix", "wires": [0, 0], "params": [], "matrix": []}]"#,
+            r#"[{"name": "H", "wires": [0], "params": [], "matrix": [[1, 0], [0, 0], [0, 0], [1, 0]]}]"#,
+        ];
+        for json in bad {
+            assert!(Circuit::from_json(json).is_err(), "{}", json);
+        }
+
+        let not_unitary = r#"[
+            {"gate": "matrix", "wires": [0], "params": [], "matrix": [[2, 0], [0, 0], [0, 0], [1, 0]]}
+        ]"#;
+        let circuit = Circuit::from_json(not_unitary).unwrap();
+        assert_eq!(circuit.ops[0].name, CUSTOM_MATRIX_GATE);
+        assert_eq!(
+            QuantumSimulator::new(1).run_circuit(&circuit),
+            Err(QsimError::NotUnitary)
+        );
+    }
+
+    #[test]
+    fn test_from_json_reports_unknown_gate_variant() {
+        let json = r#"[{"name": "H", "wires": [0], "params": []}, {"name": "Foo", "wires": [0], "params": []}]"#;
+        assert_eq!(Circuit::from_json(json), Err(QsimError::UnknownGate("Foo".to_string())));
+
+        let json = r#"[{"name": "CNOT", "wires": [0], "params": []}]"#;
+        assert!(matches!(
+            Circuit::from_json(json),
+            Err(QsimError::InvalidParameter(msg)) if msg.starts_with("Gate 0:")
+        ));
+        assert!(matches!(Circuit::from_json("not json"), Err(QsimError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_fused_circuit_is_exact_under_control() {
+        use crate::simulator::QuantumSimulator;
+
+        // RZ and RX runs carry global phases relative to their U3 form
+        let mut unitary = Circuit::new();
+        unitary
+            .add_gate("RZ", &[1], &[0.9])
+            .add_gate("RX", &[1], &[-1.3])
+            .add_gate("RZ", &[2], &[0.4])
+            .add_gate("H", &[2], &[])
+            .add_gate("CNOT", &[1, 2], &[])
+            .add_gate("RY", &[2], &[0.6])
+            .add_gate("RZ", &[2], &[-2.2]);
+        let fused = unitary.fuse();
+        assert!(fused.ops.iter().any(|op| op.name == "GlobalPhase"));
+
+        let controlled = |circuit: &Circuit| {
+            let mut sim = QuantumSimulator::new(3);
+            sim.apply_gate("Hadamard", &[0], &[]).unwrap();
+            sim.apply_controlled_circuit(0, circuit).unwrap();
+            sim
+        };
+        let reference = controlled(&unitary);
+        let merged = controlled(&fused);
+        let diff = (&reference.get_state().matrix - &merged.get_state().matrix).norm();
+        assert!(diff < 1e-12, "controlled fused state differs by {}", diff);
+
+        // Without the phase correction the control qubit picks up a relative phase
+        let mut dropped = fused.clone();
+        dropped.ops.retain(|op| op.name != "GlobalPhase");
+        let wrong = controlled(&dropped);
+        assert!((&reference.get_state().matrix - &wrong.get_state().matrix).norm() > 1e-3);
+    }
+}
@@ -1,6 +1,7 @@
-use nalgebra::{DMatrix, ComplexField};
+use crate::error::QsimError;
+use nalgebra::{DMatrix, DVector};
 use num_complex::Complex;
-use std::f64::consts::PI;
+use rand::Rng;
 
 /// Represents a quantum density matrix for an N-qubit system
 #[derive(Clone, Debug)]
@@ -9,6 +10,32 @@ pub struct DensityMatrix {
     pub num_qubits: usize,
 }
 
+/// Scratch buffers reused across `DensityMatrix::apply_unitary_in_place` calls
+#[derive(Clone, Debug)]
+pub struct UnitaryWorkspace {
+    adjoint: DMatrix<Complex<f64>>,
+    product: DMatrix<Complex<f64>>,
+}
+
+impl Default for UnitaryWorkspace {
+    fn default() -> Self {
+        UnitaryWorkspace {
+            adjoint: DMatrix::zeros(0, 0),
+            product: DMatrix::zeros(0, 0),
+        }
+    }
+}
+
+impl UnitaryWorkspace {
+    /// Make sure both buffers are `dim` x `dim`, reallocating only when the size changes
+    fn resize(&mut self, dim: usize) {
+        if self.adjoint.nrows() != dim {
+            self.adjoint = DMatrix::zeros(dim, dim);
+            self.product = DMatrix::zeros(dim, dim);
+        }
+    }
+}
+
 impl DensityMatrix {
     /// Create a new density matrix in the |0...0⟩ state
     pub fn new(num_qubits: usize) -> Self {
@@ -22,6 +49,51 @@ impl DensityMatrix {
         }
     }
 
+    /// Sample a random mixed state of the given rank
+    /// Uses the Ginibre construction ρ = G G† / Tr(G G†) with G a 2ⁿ x rank matrix of
+    /// independent complex Gaussian entries, which is Hermitian, PSD and unit-trace by construction
+    pub fn random<R: Rng>(num_qubits: usize, rank: usize, rng: &mut R) -> Self {
+        let dim = 1 << num_qubits;
+        assert!(rank >= 1 && rank <= dim, "Rank must be between 1 and {}, got {}", dim, rank);
+
+        let ginibre = DMatrix::from_fn(dim, rank, |_, _| {
+            Complex::new(standard_normal(rng), standard_normal(rng))
+        });
+        let mut matrix = &ginibre * ginibre.adjoint();
+        let trace = matrix.trace();
+        matrix /= trace;
+
+        DensityMatrix {
+            matrix,
+            num_qubits,
+        }
+    }
+
+    /// Convert to Hermitian-packed storage (lower triangle only)
+    pub fn to_packed(&self) -> PackedDensityMatrix {
+        let dim = self.dim();
+        let mut lower = Vec::with_capacity(dim * (dim + 1) / 2);
+        for i in 0..dim {
+            for j in 0..=i {
+                lower.push(self.matrix[(i, j)]);
+            }
+        }
+
+        PackedDensityMatrix {
+            lower,
+            num_qubits: self.num_qubits,
+        }
+    }
+
+    /// Rebuild the full matrix from Hermitian-packed storage
+    pub fn from_packed(packed: &PackedDensityMatrix) -> Self {
+        let dim = packed.dim();
+        DensityMatrix {
+            matrix: DMatrix::from_fn(dim, dim, |i, j| packed.get(i, j)),
+            num_qubits: packed.num_qubits,
+        }
+    }
+
     /// Calculate the trace of the density matrix
     pub fn trace(&self) -> Complex<f64> {
         self.matrix.trace()
@@ -33,6 +105,144 @@ impl DensityMatrix {
         rho_squared.trace().re
     }
 
+    /// Eigenvalues of ρ in descending order
+    pub fn eigenvalues(&self) -> Vec<f64> {
+        let mut values: Vec<f64> = self.matrix.clone().symmetric_eigenvalues().iter().copied().collect();
+        values.sort_by(|a, b| b.total_cmp(a));
+        values
+    }
+
+    /// Largest eigenvalue and its eigenvector via power iteration
+    ///
+    /// Only needs repeated matrix-vector products (O(4ⁿ) each) instead of a full O(8ⁿ)
+    /// eigendecomposition. Starts from the column of ρ with the largest diagonal entry,
+    /// nudged by a uniform vector so the start is not orthogonal to the dominant eigenvector,
+    /// and stops once the residual ‖ρv - λv‖ drops below `tol` or after `iterations` steps.
+    /// Convergence is slow when the two largest eigenvalues are nearly degenerate.
+    pub fn power_iteration(&self, iterations: usize, tol: f64) -> (f64, DVector<Complex<f64>>) {
+        let dim = self.dim();
+        let start_col = (0..dim)
+            .max_by(|&a, &b| self.matrix[(a, a)].re.total_cmp(&self.matrix[(b, b)].re))
+            .unwrap_or(0);
+        let nudge = Complex::new(1e-3 / (dim as f64).sqrt(), 0.0);
+        let mut v: DVector<Complex<f64>> = self.matrix.column(start_col).map(|x| x + nudge);
+        v /= Complex::new(v.norm(), 0.0);
+
+        let mut eigenvalue = 0.0;
+        for _ in 0..iterations {
+            let w = &self.matrix * &v;
+            eigenvalue = v.dotc(&w).re;
+            let residual = (&w - &v * Complex::new(eigenvalue, 0.0)).norm();
+            let norm = w.norm();
+            if norm == 0.0 {
+                break;
+            }
+            v = w / Complex::new(norm, 0.0);
+            if residual < tol {
+                break;
+            }
+        }
+
+        (eigenvalue, v)
+    }
+
+    /// Von Neumann entropy S(ρ) = -Tr(ρ log₂ ρ) in bits
+    pub fn von_neumann_entropy(&self) -> f64 {
+        self.eigenvalues()
+            .iter()
+            .filter(|&&v| v > 1e-12)
+            .map(|&v| -v * v.log2())
+            .sum()
+    }
+
+    /// Hilbert-Schmidt inner product Tr(ρ† σ), computed elementwise in O(d²)
+    /// For density matrices this is the overlap Tr(ρσ), a cheap proxy for fidelity
+    pub fn hilbert_schmidt_inner_product(&self, other: &DensityMatrix) -> Result<Complex<f64>, QsimError> {
+        self.check_same_dim(other)?;
+        Ok(self.matrix.iter().zip(other.matrix.iter()).map(|(a, b)| a.conj() * b).sum())
+    }
+
+    /// Second Rényi entropy S₂(ρ) = -log₂ Tr(ρ²) in bits, needing only the purity
+    pub fn renyi2_entropy(&self) -> f64 {
+        -self.purity().log2()
+    }
+
+    /// Matrix of overlaps M[(i, j)] = ⟨ψᵢ|ρ|ψⱼ⟩ for reference vectors ψ, as used by
+    /// quantum subspace expansion
+    pub fn overlaps(&self, basis: &[Vec<Complex<f64>>]) -> Result<DMatrix<Complex<f64>>, QsimError> {
+        if let Some((i, v)) = basis.iter().enumerate().find(|(_, v)| v.len() != self.dim()) {
+            return Err(QsimError::InvalidParameter(format!(
+                "Reference vector {} has length {}, expected {}",
+                i,
+                v.len(),
+                self.dim()
+            )));
+        }
+
+        let vectors = DMatrix::from_fn(self.dim(), basis.len(), |row, col| basis[col][row]);
+        Ok(vectors.adjoint() * &self.matrix * vectors)
+    }
+
+    /// Export as JSON in Qiskit's layout: {"num_qubits": n, "data": [[[re, im], ...], ...]}
+    ///
+    /// Qiskit is little-endian (qubit 0 is the least significant bit of the basis index)
+    /// while wire 0 here is the most significant, so row and column indices are
+    /// bit-reversed. In Python: `DensityMatrix(np.array(d["data"]) @ [1, 1j])`.
+    pub fn to_qiskit_json(&self) -> String {
+        let reverse = |index: usize| {
+            (0..self.num_qubits).fold(0, |acc, bit| (acc << 1) | ((index >> bit) & 1))
+        };
+        let data: Vec<Vec<[f64; 2]>> = (0..self.dim())
+            .map(|row| {
+                (0..self.dim())
+                    .map(|col| {
+                        let z = self.matrix[(reverse(row), reverse(col))];
+                        [z.re, z.im]
+                    })
+                    .collect()
+            })
+            .collect();
+
+        serde_json::json!({ "num_qubits": self.num_qubits, "data": data }).to_string()
+    }
+
+    /// Trace distance ½ Tr|ρ - σ| = ½ Σ |eigenvalues of (ρ - σ)|
+    pub fn trace_distance(&self, other: &DensityMatrix) -> Result<f64, QsimError> {
+        self.check_same_dim(other)?;
+        let diff = &self.matrix - &other.matrix;
+        Ok(0.5 * diff.symmetric_eigenvalues().iter().map(|v| v.abs()).sum::<f64>())
+    }
+
+    /// Uhlmann fidelity F(ρ, σ) = (Tr √(√ρ σ √ρ))², in [0, 1] and 1 for identical states
+    pub fn fidelity(&self, other: &DensityMatrix) -> Result<f64, QsimError> {
+        self.check_same_dim(other)?;
+        let eigen = self.matrix.clone().symmetric_eigen();
+        let roots = eigen.eigenvalues.map(|v| Complex::new(v.max(0.0).sqrt(), 0.0));
+        let sqrt_rho =
+            &eigen.eigenvectors * DMatrix::from_diagonal(&roots) * eigen.eigenvectors.adjoint();
+        let inner = &sqrt_rho * &other.matrix * &sqrt_rho;
+        let root_trace: f64 = inner.symmetric_eigenvalues().iter().map(|v| v.max(0.0).sqrt()).sum();
+        Ok(root_trace.powi(2).clamp(0.0, 1.0))
+    }
+
+    fn check_same_dim(&self, other: &DensityMatrix) -> Result<(), QsimError> {
+        if self.dim() != other.dim() {
+            return Err(QsimError::InvalidParameter(format!(
+                "States have different dimensions: {} and {}",
+                self.dim(),
+                other.dim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check that ρ is a physical state: unit trace, Hermitian and positive semidefinite within `tol`
+    pub fn is_valid(&self, tol: f64) -> bool {
+        let trace_ok = (self.trace() - Complex::new(1.0, 0.0)).norm() <= tol;
+        let hermitian_ok = (&self.matrix - self.matrix.adjoint()).norm() <= tol;
+        trace_ok && hermitian_ok && self.eigenvalues().iter().all(|&v| v >= -tol)
+    }
+
     /// Get the dimension of the Hilbert space
     pub fn dim(&self) -> usize {
         1 << self.num_qubits
@@ -40,8 +250,53 @@ impl DensityMatrix {
 
     /// Apply a unitary operator to the density matrix: ρ → U ρ U†
     pub fn apply_unitary(&mut self, unitary: &DMatrix<Complex<f64>>) {
-        let rho_new = unitary * &self.matrix * unitary.adjoint();
-        self.matrix = rho_new;
+        self.apply_unitary_in_place(unitary, &mut UnitaryWorkspace::default());
+    }
+
+    /// Apply a unitary in place, reusing the buffers in `workspace` for U† and Uρ
+    /// Once the workspace has been sized for this register no further allocation happens
+    pub fn apply_unitary_in_place(
+        &mut self,
+        unitary: &DMatrix<Complex<f64>>,
+        workspace: &mut UnitaryWorkspace,
+    ) {
+        let one = Complex::new(1.0, 0.0);
+        let zero = Complex::new(0.0, 0.0);
+        workspace.resize(self.dim());
+
+        unitary.adjoint_to(&mut workspace.adjoint);
+        workspace.product.gemm(one, unitary, &self.matrix, zero);
+        self.matrix.gemm(one, &workspace.product, &workspace.adjoint, zero);
+    }
+
+    /// Apply a diagonal unitary D = diag(phases): ρᵢⱼ → phases[i] ρᵢⱼ conj(phases[j])
+    /// Costs O(4ⁿ) with no matrix products
+    pub fn apply_diagonal(&mut self, phases: &[Complex<f64>]) {
+        assert_eq!(phases.len(), self.dim(), "Phase vector length must match the dimension");
+        for (j, mut column) in self.matrix.column_iter_mut().enumerate() {
+            let right = phases[j].conj();
+            for (entry, left) in column.iter_mut().zip(phases) {
+                *entry *= left * right;
+            }
+        }
+    }
+
+    /// Apply a Hadamard to every qubit, ρ → H⊗ⁿ ρ H⊗ⁿ, as a fast Walsh-Hadamard
+    /// transform of the columns and then the rows, in O(n·4ⁿ) instead of O(8ⁿ)
+    pub fn apply_all_hadamards(&mut self) {
+        let dim = self.dim();
+        let scale = Complex::new(1.0 / dim as f64, 0.0);
+
+        // H ρ transforms each column; (Hρ)H = (H (Hρ)ᵀ)ᵀ since H is real symmetric
+        for column in self.matrix.as_mut_slice().chunks_mut(dim) {
+            walsh_hadamard(column);
+        }
+        self.matrix.transpose_mut();
+        for column in self.matrix.as_mut_slice().chunks_mut(dim) {
+            walsh_hadamard(column);
+        }
+        self.matrix.transpose_mut();
+        self.matrix *= scale;
     }
 
     /// Apply a Kraus channel: ρ → Σᵢ Kᵢ ρ Kᵢ†
@@ -55,6 +310,152 @@ impl DensityMatrix {
         self.matrix = rho_new;
     }
 
+    /// Apply a Kraus channel and renormalize the result to unit trace
+    ///
+    /// Intended for approximate or truncated Kraus sets (e.g. approximate recovery maps)
+    /// that are not exactly trace-preserving. The renormalization hides any violation of
+    /// Σᵢ Kᵢ†Kᵢ = I, so `apply_kraus` remains the default for physical channels.
+    pub fn apply_kraus_normalized(&mut self, kraus_ops: &[DMatrix<Complex<f64>>]) {
+        self.apply_kraus(kraus_ops);
+
+        let trace = self.trace().re;
+        if trace > 0.0 {
+            self.matrix /= Complex::new(trace, 0.0);
+        }
+    }
+
+    /// Apply a global depolarizing channel on the whole register: ρ → (1-p)ρ + p Tr(ρ) I/2ⁿ
+    /// Computed directly as a mix with the identity, without any Kraus expansion
+    pub fn apply_global_depolarizing(&mut self, p: f64) {
+        if p <= 0.0 {
+            return; // No noise
+        }
+
+        let dim = self.dim();
+        let mixed_weight = p * self.trace() / dim as f64;
+        self.matrix *= Complex::new(1.0 - p, 0.0);
+        for i in 0..dim {
+            self.matrix[(i, i)] += mixed_weight;
+        }
+    }
+
+    /// Expectation value of a Pauli string such as "XIZ" (character i acts on wire i)
+    /// Computed directly from the matrix elements: P|k⟩ = c(k)|k ⊕ x⟩, so Tr(Pρ) = Σₖ c(k) ρ[k, k ⊕ x]
+    pub fn expectation_pauli(&self, pauli: &str) -> Result<f64, QsimError> {
+        pauli_expectation(self.num_qubits, pauli, |i, j| self.matrix[(i, j)])
+    }
+
+    /// Variance 1 - ⟨P⟩² of a single-shot ±1 measurement of a Pauli string
+    pub fn pauli_variance(&self, pauli: &str) -> Result<f64, QsimError> {
+        let mean = self.expectation_pauli(pauli)?;
+        Ok(1.0 - mean * mean)
+    }
+
+    /// Expectations of several Z-type observables ⟨∏Z⟩ in a single pass over the diagonal
+    /// Bit `w` of each mask selects a Z on wire `w`; a zero mask gives the trace
+    pub fn diagonal_expectations(&self, z_masks: &[usize]) -> Vec<f64> {
+        // Convert wire-indexed masks to basis-index bit positions once up front
+        let index_masks: Vec<usize> = z_masks
+            .iter()
+            .map(|&mask| {
+                (0..self.num_qubits)
+                    .filter(|w| mask & (1 << w) != 0)
+                    .fold(0, |m, w| m | (1 << (self.num_qubits - 1 - w)))
+            })
+            .collect();
+
+        let mut results = vec![0.0; z_masks.len()];
+        for i in 0..self.dim() {
+            let p = self.matrix[(i, i)].re;
+            for (result, &mask) in results.iter_mut().zip(&index_masks) {
+                if (i & mask).count_ones() % 2 == 1 {
+                    *result -= p;
+                } else {
+                    *result += p;
+                }
+            }
+        }
+        results
+    }
+
+    /// Reduced density matrix on `keep_wires`, tracing out every other qubit
+    /// Output wire i corresponds to `keep_wires[i]`; keeping no wires gives the 1x1 trace
+    pub fn partial_trace(&self, keep_wires: &[usize]) -> Result<DensityMatrix, QsimError> {
+        for (i, &w) in keep_wires.iter().enumerate() {
+            if w >= self.num_qubits {
+                return Err(QsimError::WireOutOfRange(w));
+            }
+            if keep_wires[..i].contains(&w) {
+                return Err(QsimError::InvalidParameter(format!("Wire {} listed twice", w)));
+            }
+        }
+
+        let n = self.num_qubits;
+        let traced: Vec<usize> = (0..n).filter(|w| !keep_wires.contains(w)).collect();
+        let k = keep_wires.len();
+
+        // Scatter the bits of a sub-register index onto full-register positions
+        let scatter = |index: usize, wires: &[usize]| {
+            wires.iter().enumerate().fold(0usize, |full, (pos, &w)| {
+                let bit = (index >> (wires.len() - 1 - pos)) & 1;
+                full | (bit << (n - 1 - w))
+            })
+        };
+
+        let kept_dim = 1 << k;
+        let mut reduced = DMatrix::zeros(kept_dim, kept_dim);
+        for a in 0..kept_dim {
+            let row = scatter(a, keep_wires);
+            for b in 0..kept_dim {
+                let col = scatter(b, keep_wires);
+                reduced[(a, b)] = (0..1usize << traced.len())
+                    .map(|e| {
+                        let env = scatter(e, &traced);
+                        self.matrix[(row | env, col | env)]
+                    })
+                    .sum();
+            }
+        }
+
+        Ok(DensityMatrix {
+            matrix: reduced,
+            num_qubits: k,
+        })
+    }
+
+    /// Partial transpose ρ^{T_A} over the wires in `subsystem`
+    /// Swaps the row and column bits of those wires in every matrix index
+    pub fn partial_transpose(&self, subsystem: &[usize]) -> Result<DensityMatrix, QsimError> {
+        if let Some(&w) = subsystem.iter().find(|&&w| w >= self.num_qubits) {
+            return Err(QsimError::WireOutOfRange(w));
+        }
+        let mask = subsystem
+            .iter()
+            .fold(0usize, |m, &w| m | (1 << (self.num_qubits - 1 - w)));
+
+        let matrix = DMatrix::from_fn(self.dim(), self.dim(), |i, j| {
+            let row = (i & !mask) | (j & mask);
+            let col = (j & !mask) | (i & mask);
+            self.matrix[(row, col)]
+        });
+        Ok(DensityMatrix {
+            matrix,
+            num_qubits: self.num_qubits,
+        })
+    }
+
+    /// Negativity N = Σ |λ| over the negative eigenvalues of ρ^{T_A}, with A = `subsystem`
+    /// Zero for separable states and 1/2 for a Bell pair
+    pub fn negativity(&self, subsystem: &[usize]) -> Result<f64, QsimError> {
+        Ok(self
+            .partial_transpose(subsystem)?
+            .eigenvalues()
+            .into_iter()
+            .filter(|&l| l < 0.0)
+            .map(f64::abs)
+            .sum())
+    }
+
     /// Get probability distribution from diagonal (computational basis)
     pub fn probabilities(&self) -> Vec<f64> {
         (0..self.dim())
@@ -63,9 +464,150 @@ impl DensityMatrix {
     }
 }
 
+/// Hermitian-packed density matrix storing only the lower triangle (i ≥ j), row by row
+///
+/// Uses d(d+1)/2 instead of d² complex entries, roughly halving memory for stored states.
+/// Upper-triangle entries are reconstructed on demand as ρ[i, j] = conj(ρ[j, i]).
+/// Channels are applied directly on the packed triangle with one row of scratch, so the
+/// peak during `apply_unitary`/`apply_kraus` is two packed buffers rather than a full matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackedDensityMatrix {
+    pub lower: Vec<Complex<f64>>,
+    pub num_qubits: usize,
+}
+
+impl PackedDensityMatrix {
+    /// Get the dimension of the Hilbert space
+    pub fn dim(&self) -> usize {
+        1 << self.num_qubits
+    }
+
+    /// Matrix element ρ[i, j]
+    pub fn get(&self, i: usize, j: usize) -> Complex<f64> {
+        if i >= j {
+            self.lower[i * (i + 1) / 2 + j]
+        } else {
+            self.lower[j * (j + 1) / 2 + i].conj()
+        }
+    }
+
+    /// Get probability distribution from diagonal (computational basis)
+    pub fn probabilities(&self) -> Vec<f64> {
+        (0..self.dim()).map(|i| self.get(i, i).re.max(0.0)).collect()
+    }
+
+    /// Expectation value of a Pauli string, read directly from the packed entries
+    pub fn expectation_pauli(&self, pauli: &str) -> Result<f64, QsimError> {
+        pauli_expectation(self.num_qubits, pauli, |i, j| self.get(i, j))
+    }
+
+    /// Apply a unitary operator: ρ → U ρ U†
+    pub fn apply_unitary(&mut self, unitary: &DMatrix<Complex<f64>>) {
+        self.apply_kraus(std::slice::from_ref(unitary));
+    }
+
+    /// Apply a Kraus channel: ρ → Σᵢ Kᵢ ρ Kᵢ†
+    pub fn apply_kraus(&mut self, kraus_ops: &[DMatrix<Complex<f64>>]) {
+        let dim = self.dim();
+        let mut lower = vec![Complex::new(0.0, 0.0); self.lower.len()];
+        let mut row = vec![Complex::new(0.0, 0.0); dim];
+        for k in kraus_ops {
+            for i in 0..dim {
+                // row = (K ρ)[i, ·]
+                for (l, r) in row.iter_mut().enumerate() {
+                    *r = (0..dim).map(|m| k[(i, m)] * self.get(m, l)).sum();
+                }
+                // (K ρ K†)[i, j] = Σₗ (K ρ)[i, l] conj(K[j, l]), only for j ≤ i
+                for j in 0..=i {
+                    lower[i * (i + 1) / 2 + j] += row
+                        .iter()
+                        .enumerate()
+                        .map(|(l, r)| r * k[(j, l)].conj())
+                        .sum::<Complex<f64>>();
+                }
+            }
+        }
+        self.lower = lower;
+    }
+}
+
+/// Tr(Pρ) for a Pauli string P, given an accessor for the matrix elements of ρ
+/// P|k⟩ = c(k)|k ⊕ x⟩, so Tr(Pρ) = Σₖ c(k) ρ[k, k ⊕ x]
+fn pauli_expectation<F>(num_qubits: usize, pauli: &str, element: F) -> Result<f64, QsimError>
+where
+    F: Fn(usize, usize) -> Complex<f64>,
+{
+    if pauli.chars().count() != num_qubits {
+        return Err(QsimError::InvalidParameter(format!(
+            "Pauli string '{}' has length {}, expected {}",
+            pauli,
+            pauli.chars().count(),
+            num_qubits
+        )));
+    }
+
+    let mut x_mask = 0usize;
+    let mut sign_mask = 0usize;
+    let mut num_y = 0;
+    for (wire, op) in pauli.chars().enumerate() {
+        let bit = 1 << (num_qubits - 1 - wire);
+        match op {
+            'I' => {}
+            'X' => x_mask |= bit,
+            'Y' => {
+                x_mask |= bit;
+                sign_mask |= bit;
+                num_y += 1;
+            }
+            'Z' => sign_mask |= bit,
+            _ => {
+                return Err(QsimError::InvalidParameter(format!(
+                    "Invalid Pauli operator '{}' in '{}'",
+                    op, pauli
+                )))
+            }
+        }
+    }
+
+    // Y|b⟩ = i(-1)^b |1-b⟩ contributes a global factor of i per Y
+    let y_phase = Complex::new(0.0, 1.0).powu(num_y);
+    let total: Complex<f64> = (0..1usize << num_qubits)
+        .map(|k| {
+            let sign = if (k & sign_mask).count_ones() % 2 == 1 { -1.0 } else { 1.0 };
+            element(k, k ^ x_mask) * sign
+        })
+        .sum();
+
+    Ok((total * y_phase).re)
+}
+
+/// Standard normal sample via the Box-Muller transform
+fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>(); // (0, 1], keeps ln finite
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// Unnormalized in-place fast Walsh-Hadamard transform of a length-2ⁿ vector
+fn walsh_hadamard(values: &mut [Complex<f64>]) {
+    let mut half = 1;
+    while half < values.len() {
+        for block in values.chunks_mut(2 * half) {
+            let (low, high) = block.split_at_mut(half);
+            for (a, b) in low.iter_mut().zip(high.iter_mut()) {
+                let (sum, diff) = (*a + *b, *a - *b);
+                *a = sum;
+                *b = diff;
+            }
+        }
+        half *= 2;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gates::{build_cnot_unitary, build_single_qubit_unitary, hadamard, ry};
     use approx::assert_relative_eq;
 
     #[test]
@@ -87,4 +629,406 @@ mod tests {
         rho.apply_unitary(&hadamard);
         assert_relative_eq!(rho.trace().re, 1.0, epsilon = 1e-10);
     }
+
+    #[test]
+    fn test_global_depolarizing_purity() {
+        let mut rho = DensityMatrix::new(3);
+        let p = 0.3;
+        rho.apply_global_depolarizing(p);
+
+        // Pure input: Tr(ρ'²) = (1-p)² + (2p(1-p) + p²)/d
+        let d = 8.0;
+        let expected = (1.0 - p).powi(2) + (2.0 * p * (1.0 - p) + p * p) / d;
+        assert_relative_eq!(rho.purity(), expected, epsilon = 1e-10);
+        assert_relative_eq!(rho.trace().re, 1.0, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_diagonal_expectations_match_pauli_strings() {
+        let mut rho = DensityMatrix::new(3);
+        rho.apply_unitary(&build_single_qubit_unitary(&ry(0.4), 0, 3));
+        rho.apply_unitary(&build_single_qubit_unitary(&ry(1.1), 1, 3));
+        rho.apply_unitary(&build_cnot_unitary(1, 2, 3));
+
+        // Bit w selects wire w: 0b001 = Z on wire 0, 0b110 = Z on wires 1 and 2
+        let masks = [0b001, 0b010, 0b100, 0b011, 0b110, 0b111];
+        let strings = ["ZII", "IZI", "IIZ", "ZZI", "IZZ", "ZZZ"];
+        let batched = rho.diagonal_expectations(&masks);
+
+        for (value, pauli) in batched.iter().zip(strings) {
+            assert_relative_eq!(*value, rho.expectation_pauli(pauli).unwrap(), epsilon = 1e-10);
+        }
+        assert_relative_eq!(batched[0], 0.4_f64.cos(), epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_expectation_pauli_off_diagonal() {
+        let mut rho = DensityMatrix::new(2);
+        rho.apply_unitary(&build_single_qubit_unitary(&hadamard(), 0, 2));
+        rho.apply_unitary(&build_cnot_unitary(0, 1, 2));
+
+        // Bell state |Φ+⟩: ⟨XX⟩ = 1, ⟨YY⟩ = -1, ⟨ZZ⟩ = 1, ⟨XI⟩ = 0
+        assert_relative_eq!(rho.expectation_pauli("XX").unwrap(), 1.0, epsilon = 1e-10);
+        assert_relative_eq!(rho.expectation_pauli("YY").unwrap(), -1.0, epsilon = 1e-10);
+        assert_relative_eq!(rho.expectation_pauli("ZZ").unwrap(), 1.0, epsilon = 1e-10);
+        assert_relative_eq!(rho.expectation_pauli("XI").unwrap(), 0.0, epsilon = 1e-10);
+        assert!(rho.expectation_pauli("XQ").is_err());
+        assert!(rho.expectation_pauli("X").is_err());
+    }
+
+    #[test]
+    fn test_kraus_normalized_restores_unit_trace() {
+        let mut rho = DensityMatrix::new(1);
+        rho.apply_unitary(&build_single_qubit_unitary(&hadamard(), 0, 1));
+
+        // Only the no-jump operator of amplitude damping: Σ K†K ≠ I
+        let gamma: f64 = 0.4;
+        let truncated = [DMatrix::from_row_slice(2, 2, &[
+            Complex::new(1.0, 0.0), Complex::new(0.0, 0.0),
+            Complex::new(0.0, 0.0), Complex::new((1.0 - gamma).sqrt(), 0.0),
+        ])];
+
+        let mut unnormalized = rho.clone();
+        unnormalized.apply_kraus(&truncated);
+        assert!((unnormalized.trace().re - 1.0).abs() > 0.1);
+
+        rho.apply_kraus_normalized(&truncated);
+        assert_relative_eq!(rho.trace().re, 1.0, epsilon = 1e-12);
+        assert_relative_eq!(rho.probabilities()[0], 1.0 / (2.0 - gamma), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_random_state_is_valid_with_requested_rank() {
+        use rand::prelude::*;
+
+        let mut rng = StdRng::seed_from_u64(3);
+        for rank in [1, 3, 8] {
+            let rho = DensityMatrix::random(3, rank, &mut rng);
+            assert!(rho.is_valid(1e-10));
+            let numerical_rank = rho.eigenvalues().iter().filter(|&&v| v > 1e-10).count();
+            assert_eq!(numerical_rank, rank);
+        }
+
+        let pure = DensityMatrix::random(2, 1, &mut rng);
+        assert_relative_eq!(pure.purity(), 1.0, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_power_iteration_matches_eigendecomposition() {
+        use rand::prelude::*;
+
+        let mut rng = StdRng::seed_from_u64(21);
+        for rank in [1, 3] {
+            let rho = DensityMatrix::random(3, rank, &mut rng);
+            let (value, vector) = rho.power_iteration(1000, 1e-12);
+
+            let eigen = rho.matrix.clone().symmetric_eigen();
+            let top = eigen.eigenvalues.imax();
+            assert_relative_eq!(value, eigen.eigenvalues[top], epsilon = 1e-9);
+            // Eigenvectors agree up to a global phase
+            let overlap = eigen.eigenvectors.column(top).dotc(&vector).norm();
+            assert_relative_eq!(overlap, 1.0, epsilon = 1e-8);
+        }
+    }
+
+    #[test]
+    fn test_packed_matches_dense() {
+        use rand::prelude::*;
+
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut rho = DensityMatrix::random(3, 4, &mut rng);
+        let mut packed = rho.to_packed();
+        assert_eq!(packed.lower.len(), 8 * 9 / 2);
+
+        let check = |rho: &DensityMatrix, packed: &PackedDensityMatrix| {
+            assert_eq!(rho.probabilities(), packed.probabilities());
+            for pauli in ["ZIZ", "XYI", "YYX", "IZX"] {
+                assert_relative_eq!(
+                    rho.expectation_pauli(pauli).unwrap(),
+                    packed.expectation_pauli(pauli).unwrap(),
+                    epsilon = 1e-12
+                );
+            }
+            assert!((DensityMatrix::from_packed(packed).matrix - &rho.matrix).norm() < 1e-12);
+        };
+        check(&rho, &packed);
+
+        let cnot = build_cnot_unitary(0, 2, 3);
+        rho.apply_unitary(&cnot);
+        packed.apply_unitary(&cnot);
+        check(&rho, &packed);
+
+        let gate = build_single_qubit_unitary(&ry(0.4), 1, 3);
+        rho.apply_unitary(&gate);
+        packed.apply_unitary(&gate);
+        check(&rho, &packed);
+
+        let gamma: f64 = 0.3;
+        let zero = Complex::new(0.0, 0.0);
+        let damping = [
+            DMatrix::from_row_slice(
+                2,
+                2,
+                &[Complex::new(1.0, 0.0), zero, zero, Complex::new((1.0 - gamma).sqrt(), 0.0)],
+            ),
+            DMatrix::from_row_slice(2, 2, &[zero, Complex::new(gamma.sqrt(), 0.0), zero, zero]),
+        ];
+        let kraus: Vec<_> = damping
+            .iter()
+            .map(|k| build_single_qubit_unitary(k, 2, 3))
+            .collect();
+        rho.apply_kraus(&kraus);
+        packed.apply_kraus(&kraus);
+        check(&rho, &packed);
+    }
+
+    #[test]
+    fn test_in_place_unitary_reuses_workspace() {
+        use crate::gates::{build_two_qubit_unitary, rzz};
+
+        let n = 4;
+        let layer: Vec<_> = (0..n)
+            .map(|w| build_single_qubit_unitary(&ry(0.3 + w as f64), w, n))
+            .chain((0..n - 1).map(|w| build_cnot_unitary(w, w + 1, n)))
+            .chain((0..n - 1).map(|w| build_two_qubit_unitary(&rzz(0.7), w, w + 1, n)))
+            .collect();
+
+        let mut reference = DensityMatrix::new(n);
+        let mut in_place = DensityMatrix::new(n);
+        let mut workspace = UnitaryWorkspace::default();
+        in_place.apply_unitary_in_place(&layer[0], &mut workspace);
+        reference.apply_unitary(&layer[0]);
+        let buffers = (workspace.adjoint.as_ptr(), workspace.product.as_ptr(), in_place.matrix.as_ptr());
+
+        for _ in 0..10 {
+            for unitary in &layer {
+                reference.apply_unitary(unitary);
+                in_place.apply_unitary_in_place(unitary, &mut workspace);
+            }
+        }
+
+        // No buffer was reallocated over 110 deep-circuit gates
+        assert_eq!(
+            (workspace.adjoint.as_ptr(), workspace.product.as_ptr(), in_place.matrix.as_ptr()),
+            buffers
+        );
+        assert_relative_eq!((&reference.matrix - &in_place.matrix).norm(), 0.0, epsilon = 1e-12);
+        assert_relative_eq!(in_place.trace().re, 1.0, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_hilbert_schmidt_with_self_is_purity() {
+        use rand::prelude::*;
+
+        let mut rng = StdRng::seed_from_u64(4);
+        let rho = DensityMatrix::random(2, 3, &mut rng);
+        let overlap = rho.hilbert_schmidt_inner_product(&rho).unwrap();
+        assert_relative_eq!(overlap.re, rho.purity(), epsilon = 1e-12);
+        assert_relative_eq!(overlap.im, 0.0, epsilon = 1e-12);
+
+        // Orthogonal pure states have zero overlap
+        let zero = DensityMatrix::new(1);
+        let mut one = DensityMatrix::new(1);
+        one.apply_unitary(&build_single_qubit_unitary(&ry(std::f64::consts::PI), 0, 1));
+        assert_relative_eq!(zero.hilbert_schmidt_inner_product(&one).unwrap().norm(), 0.0, epsilon = 1e-12);
+
+        assert!(rho.hilbert_schmidt_inner_product(&zero).is_err());
+    }
+
+    #[test]
+    fn test_renyi2_entropy_bounds() {
+        let mut pure = DensityMatrix::new(2);
+        pure.apply_unitary(&build_single_qubit_unitary(&hadamard(), 0, 2));
+        pure.apply_unitary(&build_cnot_unitary(0, 1, 2));
+        assert_relative_eq!(pure.renyi2_entropy(), 0.0, epsilon = 1e-12);
+
+        for n in 1..=3 {
+            let mut mixed = DensityMatrix::new(n);
+            mixed.matrix = DMatrix::identity(1 << n, 1 << n) / Complex::new((1 << n) as f64, 0.0);
+            assert_relative_eq!(mixed.renyi2_entropy(), n as f64, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_diagonal_matches_dense_z() {
+        use crate::gates::{expand_diagonal, pauli_z};
+
+        let mut dense = DensityMatrix::new(2);
+        dense.apply_unitary(&build_single_qubit_unitary(&hadamard(), 0, 2));
+        dense.apply_unitary(&build_single_qubit_unitary(&ry(0.8), 1, 2));
+        let mut diagonal = dense.clone();
+
+        dense.apply_unitary(&build_single_qubit_unitary(&pauli_z(), 1, 2));
+        let z = [Complex::new(1.0, 0.0), Complex::new(-1.0, 0.0)];
+        diagonal.apply_diagonal(&expand_diagonal(&z, &[1], 2));
+        assert_relative_eq!((&dense.matrix - &diagonal.matrix).norm(), 0.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_overlaps_in_orthonormal_basis() {
+        let mut rho = DensityMatrix::new(2);
+        rho.apply_unitary(&build_single_qubit_unitary(&ry(0.9), 0, 2));
+        rho.apply_unitary(&build_cnot_unitary(0, 1, 2));
+
+        let one = Complex::new(1.0, 0.0);
+        let basis: Vec<Vec<Complex<f64>>> = (0..4)
+            .map(|k| (0..4).map(|i| if i == k { one } else { Complex::new(0.0, 0.0) }).collect())
+            .collect();
+        let overlaps = rho.overlaps(&basis).unwrap();
+        for (k, p) in rho.probabilities().into_iter().enumerate() {
+            assert_relative_eq!(overlaps[(k, k)].re, p, epsilon = 1e-12);
+        }
+        assert_relative_eq!((overlaps[(0, 3)] - rho.matrix[(0, 3)]).norm(), 0.0, epsilon = 1e-12);
+
+        // Bell-basis vector |Φ+⟩ picks up the coherence between |00⟩ and |11⟩
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        let phi_plus = vec![one * s, Complex::new(0.0, 0.0), Complex::new(0.0, 0.0), one * s];
+        let bell = rho.overlaps(&[phi_plus]).unwrap();
+        let (c, sn) = ((0.45f64).cos(), (0.45f64).sin());
+        assert_relative_eq!(bell[(0, 0)].re, 0.5 * (c + sn).powi(2), epsilon = 1e-12);
+
+        assert!(rho.overlaps(&[vec![one; 3]]).is_err());
+    }
+
+    #[test]
+    fn test_qiskit_json_is_little_endian() {
+        // X on wire 0 gives |10⟩ here (index 2), which Qiskit labels "01" (index 1)
+        let mut rho = DensityMatrix::new(2);
+        rho.apply_unitary(&build_single_qubit_unitary(&crate::gates::pauli_x(), 0, 2));
+        let parsed: serde_json::Value = serde_json::from_str(&rho.to_qiskit_json()).unwrap();
+        assert_eq!(parsed["num_qubits"], 2);
+        assert_eq!(parsed["data"][1][1], serde_json::json!([1.0, 0.0]));
+        assert_eq!(parsed["data"][2][2], serde_json::json!([0.0, 0.0]));
+
+        // Coherence between wire-1 states keeps its sign and conjugate placement
+        let mut rho = DensityMatrix::new(2);
+        rho.apply_unitary(&build_single_qubit_unitary(&hadamard(), 1, 2));
+        rho.apply_unitary(&build_single_qubit_unitary(&crate::gates::rz(0.8), 1, 2));
+        let parsed: serde_json::Value = serde_json::from_str(&rho.to_qiskit_json()).unwrap();
+        let element = |r: usize, c: usize| {
+            Complex::new(
+                parsed["data"][r][c][0].as_f64().unwrap(),
+                parsed["data"][r][c][1].as_f64().unwrap(),
+            )
+        };
+        // Wire 1 is Qiskit qubit 1, i.e. Qiskit index 2 for |1⟩
+        assert_relative_eq!((element(0, 2) - rho.matrix[(0, 1)]).norm(), 0.0, epsilon = 1e-15);
+        assert_relative_eq!((element(2, 0) - rho.matrix[(1, 0)]).norm(), 0.0, epsilon = 1e-15);
+        assert_relative_eq!(element(0, 1).norm(), 0.0, epsilon = 1e-15);
+    }
+
+    #[test]
+    fn test_fast_hadamard_layer_matches_gates() {
+        use rand::prelude::*;
+
+        let n = 6;
+        let mut rng = StdRng::seed_from_u64(12);
+        let mut fast = DensityMatrix::random(n, 4, &mut rng);
+        let mut reference = fast.clone();
+
+        fast.apply_all_hadamards();
+        for wire in 0..n {
+            crate::gates::apply_single_qubit_gate_inplace(&mut reference, &hadamard(), wire);
+        }
+        assert_relative_eq!((&fast.matrix - &reference.matrix).norm(), 0.0, epsilon = 1e-12);
+        assert_relative_eq!(fast.trace().re, 1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_negativity_of_bell_and_product_states() {
+        let n = 2;
+        let mut bell = DensityMatrix::new(n);
+        bell.apply_unitary(&build_single_qubit_unitary(&hadamard(), 0, n));
+        bell.apply_unitary(&build_cnot_unitary(0, 1, n));
+        assert_relative_eq!(bell.negativity(&[0]).unwrap(), 0.5, epsilon = 1e-12);
+        assert_relative_eq!(bell.negativity(&[1]).unwrap(), 0.5, epsilon = 1e-12);
+        // Transposing everything is a full transpose, which keeps the spectrum
+        assert_relative_eq!(bell.negativity(&[0, 1]).unwrap(), 0.0, epsilon = 1e-12);
+
+        let mut product = DensityMatrix::new(n);
+        product.apply_unitary(&build_single_qubit_unitary(&ry(0.7), 0, n));
+        product.apply_unitary(&build_single_qubit_unitary(&hadamard(), 1, n));
+        assert_relative_eq!(product.negativity(&[0]).unwrap(), 0.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_partial_trace_of_bell_state() {
+        let mut bell = DensityMatrix::new(2);
+        bell.apply_unitary(&build_single_qubit_unitary(&hadamard(), 0, 2));
+        bell.apply_unitary(&build_cnot_unitary(0, 1, 2));
+
+        for wire in 0..2 {
+            let reduced = bell.partial_trace(&[wire]).unwrap();
+            assert_eq!(reduced.num_qubits, 1);
+            assert_relative_eq!(reduced.purity(), 0.5, epsilon = 1e-12);
+            assert_relative_eq!(reduced.matrix[(0, 0)].re, 0.5, epsilon = 1e-12);
+            assert_relative_eq!(reduced.matrix[(0, 1)].norm(), 0.0, epsilon = 1e-12);
+        }
+
+        let scalar = bell.partial_trace(&[]).unwrap();
+        assert_eq!((scalar.num_qubits, scalar.dim()), (0, 1));
+        assert_relative_eq!(scalar.matrix[(0, 0)].re, 1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_partial_trace_follows_keep_order() {
+        // RY on wire 0 only: |ψ⟩ ⊗ |0⟩ ⊗ |0⟩
+        let mut rho = DensityMatrix::new(3);
+        rho.apply_unitary(&build_single_qubit_unitary(&ry(0.7), 0, 3));
+
+        assert_eq!(rho.partial_trace(&[0, 1, 2]).unwrap().matrix, rho.matrix);
+
+        // Keeping [2, 0] puts old wire 0 in the least significant position
+        let reordered = rho.partial_trace(&[2, 0]).unwrap();
+        let p1 = (0.35_f64).sin().powi(2);
+        assert_relative_eq!(reordered.matrix[(1, 1)].re, p1, epsilon = 1e-12);
+        assert_relative_eq!(reordered.matrix[(2, 2)].re, 0.0, epsilon = 1e-12);
+        assert_relative_eq!(reordered.trace().re, 1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_fidelity_special_cases() {
+        use rand::prelude::*;
+
+        let mut plus = DensityMatrix::new(1);
+        plus.apply_unitary(&hadamard());
+        assert_relative_eq!(plus.fidelity(&plus).unwrap(), 1.0, epsilon = 1e-10);
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mixed = DensityMatrix::random(2, 3, &mut rng);
+        assert_relative_eq!(mixed.fidelity(&mixed).unwrap(), 1.0, epsilon = 1e-10);
+
+        // |0⟩ and |1⟩ are orthogonal
+        let zero = DensityMatrix::new(1);
+        let mut one = DensityMatrix::new(1);
+        one.apply_unitary(&ry(std::f64::consts::PI));
+        assert_relative_eq!(zero.fidelity(&one).unwrap(), 0.0, epsilon = 1e-10);
+
+        // Pure |ψ⟩ against σ: F = ⟨ψ|σ|ψ⟩; for |+⟩ and σ = ¾|0⟩⟨0| + ¼|1⟩⟨1| that is ½
+        let mut sigma = DensityMatrix::new(1);
+        sigma.matrix[(0, 0)] = Complex::new(0.75, 0.0);
+        sigma.matrix[(1, 1)] = Complex::new(0.25, 0.0);
+        assert_relative_eq!(plus.fidelity(&sigma).unwrap(), 0.5, epsilon = 1e-10);
+        assert_relative_eq!(sigma.fidelity(&plus).unwrap(), 0.5, epsilon = 1e-10);
+
+        // Two commuting mixed states: F = (Σ √(pᵢ qᵢ))²
+        let mut half = DensityMatrix::new(1);
+        half.matrix[(0, 0)] = Complex::new(0.5, 0.0);
+        half.matrix[(1, 1)] = Complex::new(0.5, 0.0);
+        let expected = ((0.75_f64 * 0.5).sqrt() + (0.25_f64 * 0.5).sqrt()).powi(2);
+        assert_relative_eq!(half.fidelity(&sigma).unwrap(), expected, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_bad_inputs_return_errors() {
+        let one = DensityMatrix::new(1);
+        let two = DensityMatrix::new(2);
+        assert!(matches!(one.trace_distance(&two), Err(QsimError::InvalidParameter(_))));
+        assert!(matches!(one.fidelity(&two), Err(QsimError::InvalidParameter(_))));
+
+        assert_eq!(two.partial_trace(&[2]).unwrap_err(), QsimError::WireOutOfRange(2));
+        assert!(matches!(two.partial_trace(&[1, 1]), Err(QsimError::InvalidParameter(_))));
+        assert_eq!(two.partial_transpose(&[0, 3]).unwrap_err(), QsimError::WireOutOfRange(3));
+        assert_eq!(two.negativity(&[5]), Err(QsimError::WireOutOfRange(5)));
+    }
 }
@@ -0,0 +1,37 @@
+use std::fmt;
+
+/// Error returned by the fallible simulator, circuit and mitigation routines
+#[derive(Clone, Debug, PartialEq)]
+pub enum QsimError {
+    /// Gate name not recognised by `QuantumSimulator::apply_gate`
+    UnknownGate(String),
+    /// A gate was given the wrong number of wires
+    WrongWireCount { expected: usize, got: usize },
+    /// Wire index outside the register
+    WireOutOfRange(usize),
+    /// Argument outside its valid domain; the message says which and why
+    InvalidParameter(String),
+    /// An operator required to be unitary is not
+    NotUnitary,
+    /// An observable required to be Hermitian is not
+    NotHermitian,
+    /// The state failed a physicality check; the message says which
+    Unphysical(String),
+}
+
+impl fmt::Display for QsimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QsimError::UnknownGate(name) => write!(f, "Unknown gate: {}", name),
+            QsimError::WrongWireCount { expected, got } => {
+                write!(f, "Gate requires {} wire(s), got {}", expected, got)
+            }
+            QsimError::WireOutOfRange(wire) => write!(f, "Wire {} out of range", wire),
+            QsimError::InvalidParameter(msg) | QsimError::Unphysical(msg) => write!(f, "{}", msg),
+            QsimError::NotUnitary => write!(f, "Operator is not unitary"),
+            QsimError::NotHermitian => write!(f, "Observable is not Hermitian"),
+        }
+    }
+}
+
+impl std::error::Error for QsimError {}
@@ -1,6 +1,7 @@
 use nalgebra::DMatrix;
 use num_complex::Complex;
-use std::f64::consts::PI;
+use crate::density_matrix::DensityMatrix;
+use crate::error::QsimError;
 
 /// Pauli X gate matrix
 pub fn pauli_x() -> DMatrix<Complex<f64>> {
@@ -73,6 +74,88 @@ pub fn rz(theta: f64) -> DMatrix<Complex<f64>> {
     ])
 }
 
+/// S phase gate: diag(1, i)
+pub fn s_gate() -> DMatrix<Complex<f64>> {
+    phase_gate(Complex::new(0.0, 1.0))
+}
+
+/// S† phase gate: diag(1, -i)
+pub fn s_dagger() -> DMatrix<Complex<f64>> {
+    phase_gate(Complex::new(0.0, -1.0))
+}
+
+/// T phase gate: diag(1, e^{iπ/4})
+pub fn t_gate() -> DMatrix<Complex<f64>> {
+    let r = std::f64::consts::FRAC_1_SQRT_2;
+    phase_gate(Complex::new(r, r))
+}
+
+/// T† phase gate: diag(1, e^{-iπ/4})
+pub fn t_dagger() -> DMatrix<Complex<f64>> {
+    let r = std::f64::consts::FRAC_1_SQRT_2;
+    phase_gate(Complex::new(r, -r))
+}
+
+/// diag(1, phase), written out exactly rather than via RZ (which carries a global phase)
+fn phase_gate(phase: Complex<f64>) -> DMatrix<Complex<f64>> {
+    DMatrix::from_row_slice(2, 2, &[
+        Complex::new(1.0, 0.0), Complex::new(0.0, 0.0),
+        Complex::new(0.0, 0.0), phase,
+    ])
+}
+
+/// Global phase e^{iα} I on one wire: invisible on its own, but a relative phase under control
+pub fn global_phase(alpha: f64) -> DMatrix<Complex<f64>> {
+    DMatrix::identity(2, 2) * Complex::new(0.0, alpha).exp()
+}
+
+/// General single-qubit gate U3(θ, φ, λ) = RZ(φ) RY(θ) RZ(λ) up to global phase
+pub fn u3(theta: f64, phi: f64, lambda: f64) -> DMatrix<Complex<f64>> {
+    let c = (theta / 2.0).cos();
+    let s = (theta / 2.0).sin();
+    DMatrix::from_row_slice(2, 2, &[
+        Complex::new(c, 0.0), -Complex::new(0.0, lambda).exp() * s,
+        Complex::new(0.0, phi).exp() * s, Complex::new(0.0, phi + lambda).exp() * c,
+    ])
+}
+
+/// Angles (θ, φ, λ) with U3(θ, φ, λ) equal to a 2×2 unitary up to global phase
+pub fn u3_angles(unitary: &DMatrix<Complex<f64>>) -> (f64, f64, f64) {
+    let (u00, u01, u10, u11) = (unitary[(0, 0)], unitary[(0, 1)], unitary[(1, 0)], unitary[(1, 1)]);
+    let theta = 2.0 * u10.norm().atan2(u00.norm());
+    if u00.norm() < 1e-12 {
+        // θ = π: only φ - λ is defined, so put the whole phase on φ
+        let global = (-u01).arg();
+        return (theta, u10.arg() - global, 0.0);
+    }
+    let global = u00.arg();
+    if u10.norm() < 1e-12 {
+        // θ = 0: only φ + λ is defined, so put the whole phase on λ
+        return (theta, 0.0, u11.arg() - global);
+    }
+    (theta, u10.arg() - global, (-u01).arg() - global)
+}
+
+/// 2×2 matrix of a named single-qubit gate, or None for unknown or multi-qubit names
+pub fn single_qubit_gate(name: &str, params: &[f64]) -> Option<DMatrix<Complex<f64>>> {
+    match (name, params) {
+        ("PauliX" | "X", _) => Some(pauli_x()),
+        ("PauliY" | "Y", _) => Some(pauli_y()),
+        ("PauliZ" | "Z", _) => Some(pauli_z()),
+        ("Hadamard" | "H", _) => Some(hadamard()),
+        ("S", _) => Some(s_gate()),
+        ("Sdg", _) => Some(s_dagger()),
+        ("T", _) => Some(t_gate()),
+        ("Tdg", _) => Some(t_dagger()),
+        ("RX", [theta, ..]) => Some(rx(*theta)),
+        ("RY", [theta, ..]) => Some(ry(*theta)),
+        ("RZ", [theta, ..]) => Some(rz(*theta)),
+        ("U3", [theta, phi, lambda]) => Some(u3(*theta, *phi, *lambda)),
+        ("GlobalPhase", [alpha, ..]) => Some(global_phase(*alpha)),
+        _ => None,
+    }
+}
+
 /// CNOT gate for 2-qubit system (control=0, target=1)
 /// Basis ordering: |00⟩, |01⟩, |10⟩, |11⟩
 pub fn cnot() -> DMatrix<Complex<f64>> {
@@ -94,6 +177,34 @@ pub fn cz() -> DMatrix<Complex<f64>> {
     ])
 }
 
+/// RXX Ising coupling gate: exp(-i θ/2 X⊗X)
+pub fn rxx(theta: f64) -> DMatrix<Complex<f64>> {
+    ising_coupling(&pauli_x(), theta)
+}
+
+/// RYY Ising coupling gate: exp(-i θ/2 Y⊗Y)
+pub fn ryy(theta: f64) -> DMatrix<Complex<f64>> {
+    ising_coupling(&pauli_y(), theta)
+}
+
+/// RZZ Ising coupling gate: exp(-i θ/2 Z⊗Z)
+pub fn rzz(theta: f64) -> DMatrix<Complex<f64>> {
+    ising_coupling(&pauli_z(), theta)
+}
+
+/// Canonical two-qubit interaction exp(-i(tx X⊗X + ty Y⊗Y + tz Z⊗Z))
+/// The three terms commute, so this is RXX(2tx) RYY(2ty) RZZ(2tz)
+pub fn xyz_interaction(tx: f64, ty: f64, tz: f64) -> DMatrix<Complex<f64>> {
+    rxx(2.0 * tx) * ryy(2.0 * ty) * rzz(2.0 * tz)
+}
+
+/// exp(-i θ/2 P⊗P) = cos(θ/2) I - i sin(θ/2) P⊗P, since (P⊗P)² = I
+fn ising_coupling(pauli: &DMatrix<Complex<f64>>, theta: f64) -> DMatrix<Complex<f64>> {
+    let c = Complex::new((theta / 2.0).cos(), 0.0);
+    let s = Complex::new(0.0, -(theta / 2.0).sin());
+    DMatrix::identity(4, 4) * c + kron(pauli, pauli) * s
+}
+
 /// Kronecker product of two matrices
 pub fn kron(a: &DMatrix<Complex<f64>>, b: &DMatrix<Complex<f64>>) -> DMatrix<Complex<f64>> {
     let (m, n) = (a.nrows(), a.ncols());
@@ -138,6 +249,64 @@ pub fn build_single_qubit_unitary(
     result
 }
 
+/// Build a multi-qubit unitary by applying a 4x4 two-qubit gate on wires (wire_a, wire_b)
+/// The gate's basis ordering is |wire_a wire_b⟩; the wires need not be adjacent or ordered
+pub fn build_two_qubit_unitary(
+    gate: &DMatrix<Complex<f64>>,
+    wire_a: usize,
+    wire_b: usize,
+    num_qubits: usize,
+) -> DMatrix<Complex<f64>> {
+    debug_assert!(wire_a != wire_b, "Two-qubit gate needs distinct wires, got {} twice", wire_a);
+    let dim = 1 << num_qubits;
+    let shift_a = num_qubits - 1 - wire_a;
+    let shift_b = num_qubits - 1 - wire_b;
+    let clear = !((1 << shift_a) | (1 << shift_b));
+    let mut result = DMatrix::zeros(dim, dim);
+
+    for col in 0..dim {
+        let sub_col = (((col >> shift_a) & 1) << 1) | ((col >> shift_b) & 1);
+        for sub_row in 0..4 {
+            let row = (col & clear) | ((sub_row >> 1) << shift_a) | ((sub_row & 1) << shift_b);
+            result[(row, col)] = gate[(sub_row, sub_col)];
+        }
+    }
+
+    result
+}
+
+/// Apply a 2x2 gate on `wire` directly to ρ: ρ → G ρ G†
+/// Updates pairs of rows and then pairs of columns in place, so the 2ⁿ x 2ⁿ operator
+/// from `build_single_qubit_unitary` is never formed
+pub fn apply_single_qubit_gate_inplace(
+    rho: &mut DensityMatrix,
+    gate: &DMatrix<Complex<f64>>,
+    wire: usize,
+) {
+    let dim = rho.dim();
+    let bit = 1 << (rho.num_qubits - 1 - wire);
+    let (g00, g01, g10, g11) = (gate[(0, 0)], gate[(0, 1)], gate[(1, 0)], gate[(1, 1)]);
+    let m = &mut rho.matrix;
+
+    // Left multiplication: mix rows i and i|bit
+    for col in 0..dim {
+        for i in (0..dim).filter(|i| i & bit == 0) {
+            let (a, b) = (m[(i, col)], m[(i | bit, col)]);
+            m[(i, col)] = g00 * a + g01 * b;
+            m[(i | bit, col)] = g10 * a + g11 * b;
+        }
+    }
+
+    // Right multiplication by G†: mix columns j and j|bit
+    for row in 0..dim {
+        for j in (0..dim).filter(|j| j & bit == 0) {
+            let (a, b) = (m[(row, j)], m[(row, j | bit)]);
+            m[(row, j)] = a * g00.conj() + b * g01.conj();
+            m[(row, j | bit)] = a * g10.conj() + b * g11.conj();
+        }
+    }
+}
+
 /// Build CNOT gate for multi-qubit system
 pub fn build_cnot_unitary(
     control: usize,
@@ -149,7 +318,6 @@ pub fn build_cnot_unitary(
     
     for i in 0..dim {
         let control_bit = (i >> (num_qubits - 1 - control)) & 1;
-        let target_bit = (i >> (num_qubits - 1 - target)) & 1;
         
         let j = if control_bit == 1 {
             i ^ (1 << (num_qubits - 1 - target))
@@ -163,6 +331,144 @@ pub fn build_cnot_unitary(
     result
 }
 
+/// Build Toffoli (CCX) gate for multi-qubit system: flips `target` when both controls are 1
+pub fn build_toffoli_unitary(
+    c1: usize,
+    c2: usize,
+    target: usize,
+    num_qubits: usize,
+) -> DMatrix<Complex<f64>> {
+    let dim = 1 << num_qubits;
+    let controls = (1 << (num_qubits - 1 - c1)) | (1 << (num_qubits - 1 - c2));
+    let mut result = DMatrix::zeros(dim, dim);
+
+    for i in 0..dim {
+        let j = if i & controls == controls {
+            i ^ (1 << (num_qubits - 1 - target))
+        } else {
+            i
+        };
+        result[(j, i)] = Complex::new(1.0, 0.0);
+    }
+
+    result
+}
+
+/// Build a controlled version of a single-qubit `gate` for multi-qubit system
+/// Applies `gate` to `target` on basis states whose `control` bit is set; wires may be
+/// in either order and need not be adjacent
+pub fn build_controlled_unitary(
+    control: usize,
+    target: usize,
+    gate: &DMatrix<Complex<f64>>,
+    num_qubits: usize,
+) -> DMatrix<Complex<f64>> {
+    let dim = 1 << num_qubits;
+    let control_bit = 1 << (num_qubits - 1 - control);
+    let target_shift = num_qubits - 1 - target;
+    let mut result = DMatrix::zeros(dim, dim);
+
+    for i in 0..dim {
+        if i & control_bit == 0 {
+            result[(i, i)] = Complex::new(1.0, 0.0);
+            continue;
+        }
+        let t_in = (i >> target_shift) & 1;
+        for t_out in 0..2 {
+            let j = (i & !(1 << target_shift)) | (t_out << target_shift);
+            result[(j, i)] = gate[(t_out, t_in)];
+        }
+    }
+
+    result
+}
+
+/// Build SWAP gate for multi-qubit system: exchanges the amplitudes of basis states whose
+/// bits on wires `a` and `b` differ (identity when a == b)
+pub fn build_swap_unitary(a: usize, b: usize, num_qubits: usize) -> DMatrix<Complex<f64>> {
+    let dim = 1 << num_qubits;
+    let bit_a = 1 << (num_qubits - 1 - a);
+    let bit_b = 1 << (num_qubits - 1 - b);
+    let mut result = DMatrix::zeros(dim, dim);
+
+    for i in 0..dim {
+        let j = if ((i & bit_a) == 0) != ((i & bit_b) == 0) {
+            i ^ bit_a ^ bit_b
+        } else {
+            i
+        };
+        result[(j, i)] = Complex::new(1.0, 0.0);
+    }
+
+    result
+}
+
+/// Build CZ gate for multi-qubit system: -1 phase on basis states with both `a` and `b` set
+pub fn build_cz_unitary(a: usize, b: usize, num_qubits: usize) -> DMatrix<Complex<f64>> {
+    let dim = 1 << num_qubits;
+    let both = (1 << (num_qubits - 1 - a)) | (1 << (num_qubits - 1 - b));
+    let mut result = DMatrix::zeros(dim, dim);
+
+    for i in 0..dim {
+        let sign = if i & both == both { -1.0 } else { 1.0 };
+        result[(i, i)] = Complex::new(sign, 0.0);
+    }
+
+    result
+}
+
+/// Expand the diagonal of a gate on `wires` (wires[0] is the most significant local bit)
+/// to the 2ⁿ phase vector of the full register, for use with `DensityMatrix::apply_diagonal`
+pub fn expand_diagonal(local: &[Complex<f64>], wires: &[usize], num_qubits: usize) -> Vec<Complex<f64>> {
+    (0..1usize << num_qubits)
+        .map(|i| {
+            let local_index = wires
+                .iter()
+                .fold(0, |acc, &w| (acc << 1) | ((i >> (num_qubits - 1 - w)) & 1));
+            local[local_index]
+        })
+        .collect()
+}
+
+/// Full-register matrix of a Pauli string such as "XIZ" (character i acts on wire i)
+pub fn pauli_string_matrix(pauli: &str) -> Result<DMatrix<Complex<f64>>, QsimError> {
+    pauli.chars().try_fold(DMatrix::from_element(1, 1, Complex::new(1.0, 0.0)), |acc, op| {
+        let single = match op {
+            'I' => identity(),
+            'X' => pauli_x(),
+            'Y' => pauli_y(),
+            'Z' => pauli_z(),
+            _ => {
+                return Err(QsimError::InvalidParameter(format!(
+                    "Invalid Pauli operator '{}' in '{}'",
+                    op, pauli
+                )))
+            }
+        };
+        Ok(kron(&acc, &single))
+    })
+}
+
+/// Build a controlled-phase gate: multiplies the amplitude of basis states with both
+/// the control and target bits set by e^{iλ}
+pub fn build_cphase_unitary(
+    control: usize,
+    target: usize,
+    lam: f64,
+    num_qubits: usize,
+) -> DMatrix<Complex<f64>> {
+    let dim = 1 << num_qubits;
+    let mask = (1 << (num_qubits - 1 - control)) | (1 << (num_qubits - 1 - target));
+    let phase = Complex::new(0.0, lam).exp();
+    let mut result = DMatrix::zeros(dim, dim);
+    
+    for i in 0..dim {
+        result[(i, i)] = if i & mask == mask { phase } else { Complex::new(1.0, 0.0) };
+    }
+    
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -187,4 +493,129 @@ mod tests {
         let h = hadamard();
         assert_relative_eq!(h[(0, 0)].re, 1.0 / 2.0_f64.sqrt(), epsilon = 1e-10);
     }
+
+    #[test]
+    fn test_ising_gates_unitary_and_rzz_phases() {
+        let theta = 0.83;
+        for gate in [rxx(theta), ryy(theta), rzz(theta)] {
+            let product = &gate * gate.adjoint();
+            let id = DMatrix::<Complex<f64>>::identity(4, 4);
+            assert_relative_eq!((product - id).norm(), 0.0, epsilon = 1e-10);
+        }
+
+        // RZZ(π/2) = diag(e^{-iπ/4}, e^{iπ/4}, e^{iπ/4}, e^{-iπ/4})
+        let u = rzz(std::f64::consts::FRAC_PI_2);
+        let minus = Complex::new(0.0, -std::f64::consts::FRAC_PI_4).exp();
+        let plus = Complex::new(0.0, std::f64::consts::FRAC_PI_4).exp();
+        for (i, expected) in [minus, plus, plus, minus].iter().enumerate() {
+            assert_relative_eq!((u[(i, i)] - expected).norm(), 0.0, epsilon = 1e-10);
+        }
+        assert_relative_eq!((u.clone() - DMatrix::from_diagonal(&u.diagonal())).norm(), 0.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_two_qubit_builder_matches_cnot() {
+        let cnot_full = build_two_qubit_unitary(&cnot(), 2, 0, 3);
+        assert_relative_eq!((cnot_full - build_cnot_unitary(2, 0, 3)).norm(), 0.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_inplace_single_qubit_gate_matches_dense() {
+        use rand::prelude::*;
+
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut rho = DensityMatrix::new(3);
+        rho.apply_unitary(&build_single_qubit_unitary(&hadamard(), 0, 3));
+        rho.apply_unitary(&build_cnot_unitary(0, 2, 3));
+        rho.apply_unitary(&build_single_qubit_unitary(&ry(0.9), 1, 3));
+
+        for wire in 0..3 {
+            // Random (not necessarily unitary) 2x2 gates exercise the full update rule
+            let gate = DMatrix::from_fn(2, 2, |_, _| Complex::new(rng.gen::<f64>() - 0.5, rng.gen::<f64>() - 0.5));
+            let mut dense = rho.clone();
+            dense.apply_unitary(&build_single_qubit_unitary(&gate, wire, 3));
+            let mut inplace = rho.clone();
+            apply_single_qubit_gate_inplace(&mut inplace, &gate, wire);
+
+            assert_relative_eq!((dense.matrix - inplace.matrix).norm(), 0.0, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_xyz_interaction_reduces_to_rxx() {
+        let tx = 0.37;
+        let gate = xyz_interaction(tx, 0.0, 0.0);
+        assert_relative_eq!((&gate - rxx(2.0 * tx)).norm(), 0.0, epsilon = 1e-12);
+
+        let general = xyz_interaction(0.4, -0.25, 1.3);
+        let id = DMatrix::<Complex<f64>>::identity(4, 4);
+        assert_relative_eq!((&general * general.adjoint() - id).norm(), 0.0, epsilon = 1e-12);
+
+        // exp(-i(tx XX + ty YY + tz ZZ)) from the Hermitian generator's eigendecomposition
+        let generator = kron(&pauli_x(), &pauli_x()) * Complex::new(0.4, 0.0)
+            + kron(&pauli_y(), &pauli_y()) * Complex::new(-0.25, 0.0)
+            + kron(&pauli_z(), &pauli_z()) * Complex::new(1.3, 0.0);
+        let eig = generator.symmetric_eigen();
+        let phases = eig.eigenvalues.map(|e| Complex::new(0.0, -e).exp());
+        let expected = &eig.eigenvectors * DMatrix::from_diagonal(&phases) * eig.eigenvectors.adjoint();
+        assert_relative_eq!((general - expected).norm(), 0.0, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_phase_gates_compose() {
+        assert_relative_eq!((t_gate() * t_gate() - s_gate()).norm(), 0.0, epsilon = 1e-12);
+        assert_relative_eq!((s_gate() * s_gate() - pauli_z()).norm(), 0.0, epsilon = 1e-12);
+        assert_relative_eq!((s_gate() * s_dagger() - identity()).norm(), 0.0, epsilon = 1e-12);
+        assert_relative_eq!((t_gate() * t_dagger() - identity()).norm(), 0.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_u3_reproduces_pauli_x() {
+        let x = u3(std::f64::consts::PI, 0.0, std::f64::consts::PI);
+        assert_relative_eq!((&x - pauli_x()).norm(), 0.0, epsilon = 1e-12);
+        assert!(single_qubit_gate("U3", &[0.1, 0.2]).is_none());
+        assert!(single_qubit_gate("U3", &[0.1, 0.2, 0.3, 0.4]).is_none());
+    }
+
+    #[test]
+    fn test_swap_unitary_permutes_bits() {
+        let id = DMatrix::<Complex<f64>>::identity(8, 8);
+        assert_eq!(build_swap_unitary(1, 1, 3), id);
+
+        // Non-adjacent wires 0 and 2 of three: |110⟩ (6) ↔ |011⟩ (3), |010⟩ untouched
+        let swap = build_swap_unitary(0, 2, 3);
+        assert_eq!(swap[(3, 6)], Complex::new(1.0, 0.0));
+        assert_eq!(swap[(6, 3)], Complex::new(1.0, 0.0));
+        assert_eq!(swap[(2, 2)], Complex::new(1.0, 0.0));
+        assert_eq!(&swap * &swap, id);
+        assert_eq!(build_swap_unitary(2, 0, 3), swap);
+    }
+
+    #[test]
+    fn test_cz_unitary_is_symmetric() {
+        assert_eq!(build_cz_unitary(0, 1, 2), cz());
+
+        // Non-adjacent wires 0 and 2 of three: only |101⟩ (5) and |111⟩ (7) pick up -1
+        let built = build_cz_unitary(0, 2, 3);
+        assert_eq!(build_cz_unitary(2, 0, 3), built);
+        for i in 0..8 {
+            let expected = if i == 5 || i == 7 { -1.0 } else { 1.0 };
+            assert_eq!(built[(i, i)], Complex::new(expected, 0.0));
+        }
+        assert_eq!(built, build_controlled_unitary(0, 2, &pauli_z(), 3));
+    }
+
+    #[test]
+    fn test_controlled_unitary_matches_cnot() {
+        for (control, target) in [(0, 1), (2, 0), (0, 2), (1, 0)] {
+            let built = build_controlled_unitary(control, target, &pauli_x(), 3);
+            assert_eq!(built, build_cnot_unitary(control, target, 3));
+        }
+
+        // Controlled-Z on two qubits is the 4x4 CZ
+        assert_eq!(build_controlled_unitary(1, 0, &pauli_z(), 2), cz());
+        let crz = build_controlled_unitary(0, 1, &rz(0.9), 2);
+        let id = DMatrix::<Complex<f64>>::identity(4, 4);
+        assert_relative_eq!((&crz * crz.adjoint() - id).norm(), 0.0, epsilon = 1e-12);
+    }
 }
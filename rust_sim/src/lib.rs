@@ -1,6 +1,14 @@
+pub mod circuit;
 pub mod density_matrix;
+pub mod error;
 pub mod gates;
+pub mod mitigation;
 pub mod noise_model;
+pub mod readout;
+pub mod shadows;
 pub mod simulator;
+pub mod trajectory;
 
+pub use circuit::Circuit;
+pub use error::QsimError;
 pub use simulator::QuantumSimulator;
@@ -0,0 +1,1238 @@
+use nalgebra::{DMatrix, DVector};
+use num_complex::Complex;
+use crate::circuit::Circuit;
+use crate::density_matrix::DensityMatrix;
+use crate::error::QsimError;
+use crate::noise_model::{Channel, NoiseModel};
+use crate::readout::{counts_to_distribution, parity_expectation};
+use crate::simulator::QuantumSimulator;
+use std::collections::{HashMap, HashSet};
+
+/// User-supplied extrapolator mapping (scale factor, expectation) points to a zero-noise value
+pub type Extrapolator = Box<dyn Fn(&[(f64, f64)]) -> f64>;
+
+/// Fit model used to extrapolate expectation values to the zero-noise limit
+pub enum Extrapolation {
+    /// Least-squares straight line through all points
+    Linear,
+    /// Least-squares parabola through all points (needs at least 3)
+    Quadratic,
+    /// Richardson extrapolation: polynomial of degree n-1 through all n points
+    Richardson,
+    /// Arbitrary user fit over the (scale factor, expectation) points
+    Custom(Extrapolator),
+}
+
+impl std::fmt::Debug for Extrapolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Extrapolation::Linear => write!(f, "Linear"),
+            Extrapolation::Quadratic => write!(f, "Quadratic"),
+            Extrapolation::Richardson => write!(f, "Richardson"),
+            Extrapolation::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// Zero-noise extrapolation of expectation values measured at several noise scale factors
+pub fn zero_noise_extrapolation(
+    scale_factors: &[f64],
+    expectations: &[f64],
+    method: Extrapolation,
+) -> Result<f64, QsimError> {
+    if let Extrapolation::Custom(extrapolate) = &method {
+        if scale_factors.len() != expectations.len() {
+            return Err(QsimError::InvalidParameter(format!(
+                "Got {} scale factors for {} expectation values",
+                scale_factors.len(),
+                expectations.len()
+            )));
+        }
+        let points: Vec<(f64, f64)> =
+            scale_factors.iter().copied().zip(expectations.iter().copied()).collect();
+        return Ok(extrapolate(&points));
+    }
+
+    let weights = extrapolation_weights(scale_factors, expectations.len(), &method)?;
+    Ok(weights.dot(&DVector::from_column_slice(expectations)))
+}
+
+/// Zero-noise value of a least-squares line through (scale factor, expectation) points
+pub fn zne_linear(observables: &[f64], scale_factors: &[f64]) -> Result<f64, QsimError> {
+    zero_noise_extrapolation(scale_factors, observables, Extrapolation::Linear)
+}
+
+/// Zero-noise value of a least-squares parabola through (scale factor, expectation) points
+pub fn zne_quadratic(observables: &[f64], scale_factors: &[f64]) -> Result<f64, QsimError> {
+    zero_noise_extrapolation(scale_factors, observables, Extrapolation::Quadratic)
+}
+
+/// Zero-noise extrapolation driven by a user routine: `expectation_at(λ)` runs the
+/// noise-amplified experiment (e.g. a folded circuit) at scale λ and returns ⟨O⟩
+pub fn zne_from_fn<F>(
+    scale_factors: &[f64],
+    mut expectation_at: F,
+    method: Extrapolation,
+) -> Result<f64, QsimError>
+where
+    F: FnMut(f64) -> f64,
+{
+    let expectations: Vec<f64> = scale_factors.iter().map(|&scale| expectation_at(scale)).collect();
+    zero_noise_extrapolation(scale_factors, &expectations, method)
+}
+
+/// Zero-noise extrapolation that also returns the standard error of the mitigated value
+///
+/// `variances` are the sampling variances of each input expectation (e.g. (1 - ⟨P⟩²) / shots).
+/// The zero-noise estimate is the intercept of a least-squares fit, i.e. a fixed linear
+/// combination wᵀy of the inputs, so its variance is the [0, 0] element of the fit's
+/// covariance A Σ Aᵀ = Σᵢ wᵢ² σᵢ² for independent samples.
+/// Returns: (mitigated_value, standard_error)
+pub fn zne_with_uncertainty(
+    scale_factors: &[f64],
+    expectations: &[f64],
+    variances: &[f64],
+    method: Extrapolation,
+) -> Result<(f64, f64), QsimError> {
+    if variances.len() != expectations.len() {
+        return Err(QsimError::InvalidParameter(format!(
+            "Got {} variances for {} expectation values",
+            variances.len(),
+            expectations.len()
+        )));
+    }
+    if variances.iter().any(|&v| v < 0.0) {
+        return Err(QsimError::InvalidParameter("Variances must be non-negative".to_string()));
+    }
+
+    let weights = extrapolation_weights(scale_factors, expectations.len(), &method)?;
+    let value = weights.dot(&DVector::from_column_slice(expectations));
+    let variance: f64 = weights
+        .iter()
+        .zip(variances)
+        .map(|(w, v)| w * w * v)
+        .sum();
+
+    Ok((value, variance.sqrt()))
+}
+
+/// Coefficients w such that the extrapolated zero-noise value is wᵀy
+///
+/// This is the first row of the least-squares solution operator (XᵀX)⁻¹Xᵀ for the
+/// polynomial design matrix X of the chosen model.
+fn extrapolation_weights(
+    scale_factors: &[f64],
+    n_values: usize,
+    method: &Extrapolation,
+) -> Result<DVector<f64>, QsimError> {
+    let n = scale_factors.len();
+    if n != n_values {
+        return Err(QsimError::InvalidParameter(format!(
+            "Got {} scale factors for {} expectation values",
+            n, n_values
+        )));
+    }
+    if n < 2 {
+        return Err(QsimError::InvalidParameter(
+            "Extrapolation requires at least 2 scale factors".to_string(),
+        ));
+    }
+
+    let degree = match method {
+        Extrapolation::Linear => 1,
+        Extrapolation::Quadratic => 2,
+        Extrapolation::Richardson => n - 1,
+        Extrapolation::Custom(_) => {
+            return Err(QsimError::InvalidParameter(
+                "Custom extrapolation is not a linear fit and has no weights".to_string(),
+            ))
+        }
+    };
+    if degree >= n {
+        return Err(QsimError::InvalidParameter(format!(
+            "{:?} extrapolation requires at least {} scale factors, got {}",
+            method,
+            degree + 1,
+            n
+        )));
+    }
+
+    // Vandermonde design matrix: X[i][k] = λᵢ^k
+    let x = DMatrix::from_fn(n, degree + 1, |i, k| scale_factors[i].powi(k as i32));
+    let normal = x.transpose() * &x;
+    let normal_inv = normal
+        .try_inverse()
+        .ok_or_else(|| QsimError::InvalidParameter("Scale factors must be distinct".to_string()))?;
+    let solver = normal_inv * x.transpose();
+
+    Ok(solver.row(0).transpose())
+}
+
+/// Depolarizing strength p for which ρ → (1-p)ρ + p I/d on `num_qubits` qubits has
+/// the given average gate fidelity, inverting F = 1 - p (d - 1)/d
+pub fn fit_depolarizing_to_fidelity(target_fidelity: f64, num_qubits: usize) -> Result<f64, QsimError> {
+    let d = (1usize << num_qubits) as f64;
+    check_fidelity_range(target_fidelity, 1.0 / d, "depolarizing")?;
+    Ok((1.0 - target_fidelity) * d / (d - 1.0))
+}
+
+/// Single-qubit amplitude damping strength gamma with the given average gate fidelity,
+/// inverting F = ((1 + sqrt(1 - gamma))² + 2) / 6
+pub fn fit_amplitude_damping_to_fidelity(target_fidelity: f64) -> Result<f64, QsimError> {
+    check_fidelity_range(target_fidelity, 0.5, "amplitude damping")?;
+    let root = (6.0 * target_fidelity - 2.0).sqrt() - 1.0;
+    Ok(1.0 - root * root)
+}
+
+/// Single-qubit dephasing strength lambda with the given average gate fidelity,
+/// inverting F = 1 - 2 lambda / 3
+pub fn fit_dephasing_to_fidelity(target_fidelity: f64) -> Result<f64, QsimError> {
+    check_fidelity_range(target_fidelity, 2.0 / 3.0, "dephasing")?;
+    Ok(1.5 * (1.0 - target_fidelity))
+}
+
+fn check_fidelity_range(target: f64, min: f64, channel: &str) -> Result<(), QsimError> {
+    if !(min..=1.0).contains(&target) {
+        return Err(QsimError::InvalidParameter(format!(
+            "Target fidelity {} is outside the range [{:.4}, 1] reachable by {} noise",
+            target, min, channel
+        )));
+    }
+    Ok(())
+}
+
+/// Partition Pauli terms into groups of mutually qubit-wise commuting strings
+///
+/// Two strings commute qubit-wise when, on every wire, their operators are equal or one of
+/// them is I, so a whole group can be measured with a single basis setting. Terms are placed
+/// greedily into the first compatible group. Returns indices into `terms`.
+pub fn group_commuting(terms: &[(f64, String)]) -> Vec<Vec<usize>> {
+    let qubit_wise_commute = |a: &str, b: &str| {
+        a.len() == b.len()
+            && a.chars().zip(b.chars()).all(|(x, y)| x == y || x == 'I' || y == 'I')
+    };
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (idx, (_, pauli)) in terms.iter().enumerate() {
+        let compatible = groups
+            .iter_mut()
+            .find(|group| group.iter().all(|&j| qubit_wise_commute(pauli, &terms[j].1)));
+        match compatible {
+            Some(group) => group.push(idx),
+            None => groups.push(vec![idx]),
+        }
+    }
+    groups
+}
+
+/// Estimate ⟨H⟩ for H = Σ cᵢ Pᵢ from sampled shots, with one measurement setting per
+/// qubit-wise commuting group and `n_shots` shots per setting
+pub fn estimate_hamiltonian_sampled(
+    sim: &QuantumSimulator,
+    terms: &[(f64, String)],
+    n_shots: usize,
+) -> Result<f64, QsimError> {
+    let mut energy = 0.0;
+    for group in group_commuting(terms) {
+        // The group's measurement basis takes the non-identity operator on each wire
+        let basis: String = (0..terms[group[0]].1.len())
+            .map(|w| {
+                group
+                    .iter()
+                    .filter_map(|&j| terms[j].1.chars().nth(w))
+                    .find(|&op| op != 'I')
+                    .unwrap_or('Z')
+            })
+            .collect();
+        let counts: HashMap<String, f64> = sim
+            .measure_counts_in_basis(&basis, n_shots)?
+            .into_iter()
+            .map(|(bits, c)| (bits, c as f64))
+            .collect();
+
+        for &j in &group {
+            let (coeff, pauli) = &terms[j];
+            let support: Vec<usize> = pauli
+                .chars()
+                .enumerate()
+                .filter(|&(_, op)| op != 'I')
+                .map(|(w, _)| w)
+                .collect();
+            energy += coeff * parity_expectation(&counts, &support);
+        }
+    }
+    Ok(energy)
+}
+
+/// CHSH correlator S = E(a₀,b₀) + E(a₀,b₁) + E(a₁,b₀) - E(a₁,b₁) on two qubits
+///
+/// `angles` = [a₀, a₁, b₀, b₁] are measurement directions in the X-Z plane, i.e. the
+/// observable at angle θ is cos θ Z + sin θ X. Local hidden-variable models obey |S| ≤ 2,
+/// while a Bell state with angles [0, π/2, π/4, -π/4] reaches 2√2.
+pub fn chsh_value(
+    sim: &QuantumSimulator,
+    qubit_a: usize,
+    qubit_b: usize,
+    angles: [f64; 4],
+) -> Result<f64, QsimError> {
+    let num_qubits = sim.get_state().num_qubits;
+    if qubit_a >= num_qubits || qubit_b >= num_qubits || qubit_a == qubit_b {
+        return Err(QsimError::InvalidParameter(format!(
+            "CHSH needs two distinct wires below {}, got {} and {}",
+            num_qubits, qubit_a, qubit_b
+        )));
+    }
+
+    let pauli_on = |op_a: char, op_b: char| -> String {
+        (0..num_qubits)
+            .map(|w| match w {
+                w if w == qubit_a => op_a,
+                w if w == qubit_b => op_b,
+                _ => 'I',
+            })
+            .collect()
+    };
+    let zz = sim.expectation_pauli(&pauli_on('Z', 'Z'))?;
+    let zx = sim.expectation_pauli(&pauli_on('Z', 'X'))?;
+    let xz = sim.expectation_pauli(&pauli_on('X', 'Z'))?;
+    let xx = sim.expectation_pauli(&pauli_on('X', 'X'))?;
+
+    // E(θa, θb) = ⟨(cos θa Z + sin θa X) ⊗ (cos θb Z + sin θb X)⟩
+    let correlation = |theta_a: f64, theta_b: f64| -> f64 {
+        let (ca, sa) = (theta_a.cos(), theta_a.sin());
+        let (cb, sb) = (theta_b.cos(), theta_b.sin());
+        ca * cb * zz + ca * sb * zx + sa * cb * xz + sa * sb * xx
+    };
+
+    let [a0, a1, b0, b1] = angles;
+    Ok(correlation(a0, b0) + correlation(a0, b1) + correlation(a1, b0) - correlation(a1, b1))
+}
+
+/// Approximate Kraus set of the noisy process a single qubit experiences over a circuit
+///
+/// Performs process tomography on `wire`: each operator basis element |i⟩⟨j| is prepared on
+/// `wire` (all other qubits in |0⟩), the circuit is run under `noise`, and the output is
+/// reduced to `wire`. The resulting Choi matrix is diagonalized to obtain Kraus operators.
+/// Correlations with other qubits are discarded, so this is exact only when `wire` does not
+/// interact with the rest of the register.
+pub fn effective_single_qubit_channel(
+    circuit: &Circuit,
+    noise: &NoiseModel,
+    wire: usize,
+) -> Result<Vec<DMatrix<Complex<f64>>>, QsimError> {
+    let num_qubits = circuit.num_qubits().max(wire + 1);
+    let bit = 1 << (num_qubits - 1 - wire);
+
+    // Choi matrix J[(2i + a, 2j + b)] = E(|i⟩⟨j|)[(a, b)]
+    let mut choi = DMatrix::zeros(4, 4);
+    for i in 0..2 {
+        for j in 0..2 {
+            let mut sim = QuantumSimulator::new(num_qubits);
+            let state = sim.state_mut();
+            state.matrix[(0, 0)] = Complex::new(0.0, 0.0);
+            state.matrix[(i * bit, j * bit)] = Complex::new(1.0, 0.0);
+
+            sim.run_circuit_noisy(circuit, noise)?;
+            let reduced = sim.get_state().partial_trace(&[wire])?;
+            for a in 0..2 {
+                for b in 0..2 {
+                    choi[(2 * i + a, 2 * j + b)] = reduced.matrix[(a, b)];
+                }
+            }
+        }
+    }
+
+    // J = Σₖ λₖ |vₖ⟩⟨vₖ| gives Kₖ[(a, i)] = sqrt(λₖ) vₖ[2i + a]
+    let eigen = choi.symmetric_eigen();
+    let kraus = eigen
+        .eigenvalues
+        .iter()
+        .zip(eigen.eigenvectors.column_iter())
+        .filter(|(&lambda, _)| lambda > 1e-12)
+        .map(|(&lambda, v)| DMatrix::from_fn(2, 2, |a, i| v[2 * i + a] * lambda.sqrt()))
+        .collect();
+
+    Ok(kraus)
+}
+
+/// Steady state of a channel applied repeatedly to `wire`, starting from the maximally
+/// mixed state on `num_qubits` qubits
+/// Stops once successive iterates differ by less than 1e-12 (Frobenius norm) or after
+/// `iterations` applications
+pub fn channel_fixed_point(
+    channel: &Channel,
+    wire: usize,
+    num_qubits: usize,
+    iterations: usize,
+) -> DensityMatrix {
+    let dim = 1 << num_qubits;
+    let mut rho = DensityMatrix::new(num_qubits);
+    rho.matrix = DMatrix::identity(dim, dim) / Complex::new(dim as f64, 0.0);
+
+    for _ in 0..iterations {
+        let previous = rho.matrix.clone();
+        channel.apply(&mut rho, wire);
+        if (&rho.matrix - previous).norm() < 1e-12 {
+            break;
+        }
+    }
+
+    rho
+}
+
+/// Quick figure of merit for the total error a circuit accumulates under `noise`
+/// Sums the single-qubit rate per one-wire gate, the two-qubit rate per multi-wire gate,
+/// and (idle_gamma + idle_lambda) for every wire left idle in every layer. This is a
+/// first-order count of error events, not a simulated infidelity.
+pub fn estimate_circuit_error(circuit: &Circuit, noise: &NoiseModel) -> f64 {
+    let num_qubits = circuit.num_qubits();
+    let idle_rate = noise.idle_gamma + noise.idle_lambda;
+
+    let gate_error: f64 = circuit
+        .ops
+        .iter()
+        .map(|op| {
+            if op.wires.len() > 1 {
+                noise.two_qubit_error
+            } else {
+                noise.single_qubit_error
+            }
+        })
+        .sum();
+
+    let idle_error: f64 = circuit
+        .layers()
+        .iter()
+        .map(|layer| {
+            let mut busy = vec![false; num_qubits];
+            for &idx in layer {
+                for &w in &circuit.ops[idx].wires {
+                    busy[w] = true;
+                }
+            }
+            busy.iter().filter(|&&b| !b).count() as f64 * idle_rate
+        })
+        .sum();
+
+    gate_error + idle_error
+}
+
+/// Quantum phase estimation of the unitary implemented by `unitary_circuit`
+///
+/// The counting register is put in uniform superposition, counting_qubits[j] controls
+/// U^(2^j), and an inverse QFT maps the phase onto the register. With counting_qubits[0]
+/// as the most significant bit, one sampled readout y estimates the eigenphase φ ≈ y / 2^t
+/// of the state prepared on `target_qubits`. (Ordering the powers this way absorbs the
+/// bit reversal of `Circuit::qft`, which omits its final swaps.)
+pub fn phase_estimation(
+    sim: &mut QuantumSimulator,
+    unitary_circuit: &Circuit,
+    counting_qubits: &[usize],
+    target_qubits: &[usize],
+) -> Result<usize, QsimError> {
+    if counting_qubits.is_empty() {
+        return Err(QsimError::InvalidParameter(
+            "Phase estimation needs at least one counting qubit".to_string(),
+        ));
+    }
+    if let Some(w) = counting_qubits.iter().find(|w| target_qubits.contains(w)) {
+        return Err(QsimError::InvalidParameter(format!("Wire {} is both a counting and a target qubit", w)));
+    }
+    if let Some(op) = unitary_circuit
+        .ops
+        .iter()
+        .find(|op| op.wires.iter().any(|w| !target_qubits.contains(w)))
+    {
+        return Err(QsimError::InvalidParameter(format!("Gate {} acts outside the target register", op.name)));
+    }
+
+    for &w in counting_qubits {
+        sim.apply_gate("Hadamard", &[w], &[])?;
+    }
+    for (j, &control) in counting_qubits.iter().enumerate() {
+        for _ in 0..1usize << j {
+            sim.apply_controlled_circuit(control, unitary_circuit)?;
+        }
+    }
+    sim.run_circuit(&Circuit::qft(counting_qubits).inverse())?;
+
+    let bits = sim.measure();
+    let t = counting_qubits.len();
+    Ok(counting_qubits
+        .iter()
+        .enumerate()
+        .fold(0, |y, (j, &w)| y | (bits[w] << (t - 1 - j))))
+}
+
+/// Barnum–Knill transpose-channel (Petz) recovery for `noise` acting on a code space
+///
+/// Rₖ = P Eₖ† N^(-1/2) with N = Σₖ Eₖ P Eₖ† the image of the code projector P; the inverse
+/// square root is taken on the support of N. One extra Kraus operator, the projector onto
+/// the complement of that support, makes the recovery trace preserving. The Kraus
+/// operators of `noise` must act on the same space as `code_space_projector`.
+pub fn transpose_channel_recovery(
+    noise: &Channel,
+    code_space_projector: &DMatrix<Complex<f64>>,
+) -> Channel {
+    let p = code_space_projector;
+    let dim = p.nrows();
+    let image: DMatrix<Complex<f64>> = noise
+        .kraus
+        .iter()
+        .fold(DMatrix::zeros(dim, dim), |acc, e| acc + e * p * e.adjoint());
+
+    let eigen = image.symmetric_eigen();
+    let mut inv_sqrt = DMatrix::zeros(dim, dim);
+    let mut complement = DMatrix::identity(dim, dim);
+    for (&lambda, v) in eigen.eigenvalues.iter().zip(eigen.eigenvectors.column_iter()) {
+        if lambda > 1e-12 {
+            let outer = v * v.adjoint();
+            inv_sqrt += &outer * Complex::new(1.0 / lambda.sqrt(), 0.0);
+            complement -= outer;
+        }
+    }
+
+    let mut kraus: Vec<DMatrix<Complex<f64>>> =
+        noise.kraus.iter().map(|e| p * e.adjoint() * &inv_sqrt).collect();
+    if complement.norm() > 1e-12 {
+        kraus.push(complement);
+    }
+    Channel::new(kraus)
+}
+
+/// Number of shots N so that the standard error sqrt(variance / N) of an expectation value
+/// is at most `target_stderr`. For a ±1 observable the variance is 1 - ⟨P⟩² ≤ 1, so
+/// `variance_estimate = 1.0` gives a worst-case budget (see `DensityMatrix::pauli_variance`).
+pub fn shots_for_precision(variance_estimate: f64, target_stderr: f64) -> Result<usize, QsimError> {
+    if target_stderr <= 0.0 {
+        return Err(QsimError::InvalidParameter(format!(
+            "Target standard error must be positive, got {}",
+            target_stderr
+        )));
+    }
+    if variance_estimate < 0.0 {
+        return Err(QsimError::InvalidParameter(format!(
+            "Variance must be non-negative, got {}",
+            variance_estimate
+        )));
+    }
+    // Small slack so round-off in the division does not add a spurious extra shot
+    let shots = (variance_estimate / target_stderr.powi(2) - 1e-9).ceil();
+    Ok((shots as usize).max(1))
+}
+
+/// Logical error rate of a distance-d bit-flip repetition code after `rounds` rounds of
+/// independent bit flips with probability `physical_error`, each followed by majority-vote
+/// decoding and re-encoding. Computed exactly from the binomial distribution, i.e. the
+/// many-trial limit of a Monte Carlo estimate; ties for even d are broken by a fair coin.
+/// Below the threshold p = 0.5 the rate falls with distance.
+pub fn repetition_code_logical_error(
+    distance: usize,
+    physical_error: f64,
+    rounds: usize,
+) -> Result<f64, QsimError> {
+    if distance == 0 {
+        return Err(QsimError::InvalidParameter(
+            "Repetition code needs at least one physical qubit".to_string(),
+        ));
+    }
+    if !(0.0..=1.0).contains(&physical_error) {
+        return Err(QsimError::InvalidParameter(format!(
+            "Physical error rate must be in [0, 1], got {}",
+            physical_error
+        )));
+    }
+    let p = physical_error;
+
+    // P(k of d bits flip) = C(d, k) p^k (1-p)^(d-k); majority vote fails once k > d/2
+    let mut binomial = 1.0;
+    let mut round_failure = 0.0;
+    for k in 0..=distance {
+        let weight = binomial * p.powi(k as i32) * (1.0 - p).powi((distance - k) as i32);
+        if 2 * k > distance {
+            round_failure += weight;
+        } else if 2 * k == distance {
+            round_failure += 0.5 * weight;
+        }
+        binomial *= (distance - k) as f64 / (k + 1) as f64;
+    }
+
+    // The logical bit is wrong after an odd number of failed rounds
+    Ok((1.0 - (1.0 - 2.0 * round_failure).powi(rounds as i32)) / 2.0)
+}
+
+/// Before/after comparison of measured distributions against an ideal one
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComparisonReport {
+    /// Total variation distance ½ Σ |p - q| of `before` from `ideal`
+    pub before_tvd: f64,
+    /// Total variation distance of `after` from `ideal`
+    pub after_tvd: f64,
+    /// Hellinger fidelity (Σ √(p q))² of `before` with `ideal`
+    pub before_hellinger_fidelity: f64,
+    /// Hellinger fidelity of `after` with `ideal`
+    pub after_hellinger_fidelity: f64,
+    /// p_after - p_before for every bitstring seen in any of the three histograms
+    pub deltas: HashMap<String, f64>,
+}
+
+/// Compare histograms before and after mitigation with the ideal histogram
+/// Counts are normalized to probabilities first, so the shot totals may differ
+pub fn compare_distributions(
+    before: &HashMap<String, usize>,
+    after: &HashMap<String, usize>,
+    ideal: &HashMap<String, usize>,
+) -> Result<ComparisonReport, QsimError> {
+    let before = normalize_counts(before)?;
+    let after = normalize_counts(after)?;
+    let ideal = normalize_counts(ideal)?;
+
+    let keys: HashSet<&String> = before.keys().chain(after.keys()).chain(ideal.keys()).collect();
+    let prob = |dist: &HashMap<String, f64>, key: &String| dist.get(key).copied().unwrap_or(0.0);
+    let tvd = |dist: &HashMap<String, f64>| {
+        0.5 * keys.iter().map(|k| (prob(dist, k) - prob(&ideal, k)).abs()).sum::<f64>()
+    };
+    let hellinger = |dist: &HashMap<String, f64>| {
+        keys.iter().map(|k| (prob(dist, k) * prob(&ideal, k)).sqrt()).sum::<f64>().powi(2)
+    };
+
+    Ok(ComparisonReport {
+        before_tvd: tvd(&before),
+        after_tvd: tvd(&after),
+        before_hellinger_fidelity: hellinger(&before),
+        after_hellinger_fidelity: hellinger(&after),
+        deltas: keys
+            .iter()
+            .map(|&k| (k.clone(), prob(&after, k) - prob(&before, k)))
+            .collect(),
+    })
+}
+
+/// Total variation distance ½ Σ |pᵢ - qᵢ| between two probability vectors
+/// Equals the largest difference in probability the two assign to any event
+pub fn total_variation_distance(p: &[f64], q: &[f64]) -> Result<f64, QsimError> {
+    if p.len() != q.len() {
+        return Err(QsimError::InvalidParameter(format!(
+            "Distributions have different lengths: {} and {}",
+            p.len(),
+            q.len()
+        )));
+    }
+    Ok(0.5 * p.iter().zip(q).map(|(a, b)| (a - b).abs()).sum::<f64>())
+}
+
+/// Share of a circuit's observable error attributable to each qubit's noise
+///
+/// Runs the circuit ideally, under the full `model`, and once per qubit with only that
+/// qubit's noise silenced (its `qubit_noise_scale` set to 0). A qubit's share is the error
+/// removed by silencing it divided by the total error; shares need not sum to 1 when
+/// errors on different qubits interact.
+pub fn error_attribution(
+    circuit: &Circuit,
+    observable: &str,
+    model: &NoiseModel,
+) -> Result<HashMap<usize, f64>, QsimError> {
+    let num_qubits = circuit.num_qubits();
+    let run = |noise: &NoiseModel| -> Result<f64, QsimError> {
+        let mut sim = QuantumSimulator::new(num_qubits);
+        sim.run_circuit_noisy(circuit, noise)?;
+        sim.expectation_pauli(observable)
+    };
+
+    let ideal = run(&NoiseModel::noiseless())?;
+    let total_error = (run(model)? - ideal).abs();
+
+    (0..num_qubits)
+        .map(|qubit| {
+            let mut silenced = model.clone();
+            if silenced.qubit_noise_scale.len() < num_qubits {
+                silenced.qubit_noise_scale.resize(num_qubits, 1.0);
+            }
+            silenced.qubit_noise_scale[qubit] = 0.0;
+            let remaining = (run(&silenced)? - ideal).abs();
+            let share = if total_error > 0.0 {
+                (total_error - remaining) / total_error
+            } else {
+                0.0
+            };
+            Ok((qubit, share))
+        })
+        .collect()
+}
+
+/// Average gate infidelity 1 - F_avg that `noise` adds to a single named gate
+///
+/// Each of the `num_qubits` system wires is paired with a reference wire in a Bell state,
+/// so the system sees a maximally mixed input. The process fidelity F_pro is the overlap of
+/// the noisy and ideal Choi states, and F_avg = (d·F_pro + 1) / (d + 1) with d = 2^n.
+pub fn gate_noise_impact(
+    gate_name: &str,
+    wires: &[usize],
+    params: &[f64],
+    noise: &NoiseModel,
+    num_qubits: usize,
+) -> Result<f64, QsimError> {
+    if let Some(&wire) = wires.iter().find(|&&w| w >= num_qubits) {
+        return Err(QsimError::WireOutOfRange(wire));
+    }
+
+    let bell_pairs = || -> Result<QuantumSimulator, QsimError> {
+        let mut sim = QuantumSimulator::new(2 * num_qubits);
+        for wire in 0..num_qubits {
+            sim.apply_gate("Hadamard", &[wire], &[])?;
+            sim.apply_gate("CNOT", &[wire, num_qubits + wire], &[])?;
+        }
+        Ok(sim)
+    };
+    let mut ideal = bell_pairs()?;
+    ideal.apply_gate(gate_name, wires, params)?;
+    let mut noisy = bell_pairs()?;
+    noisy.apply_gate_noisy(gate_name, wires, params, noise)?;
+
+    let process_fidelity = ideal
+        .get_state()
+        .hilbert_schmidt_inner_product(noisy.get_state())?
+        .re;
+    let dim = (1usize << num_qubits) as f64;
+    Ok(1.0 - (dim * process_fidelity + 1.0) / (dim + 1.0))
+}
+
+/// Undo the (1 - p) shrinkage of an expectation value under global depolarizing noise
+/// ρ → (1-p)ρ + p I/2ⁿ leaves traceless observables at (1-p)⟨O⟩, so dividing recovers ⟨O⟩
+pub fn depolarizing_rescale(noisy_expectation: f64, p: f64) -> f64 {
+    noisy_expectation / (1.0 - p)
+}
+
+/// Calibrate the global depolarizing strength p that best describes `circuit` under `noise`
+///
+/// The reference observable is the projector onto the ideal output |ψ⟩, whose ideal value
+/// is 1. Under global depolarizing its noisy value is F = (1-p) + p/d, so p = (1-F)/(1-1/d).
+pub fn estimate_global_depolarizing(circuit: &Circuit, noise: &NoiseModel) -> Result<f64, QsimError> {
+    let num_qubits = circuit.num_qubits();
+    let mut ideal = QuantumSimulator::new(num_qubits);
+    ideal.run_circuit(circuit)?;
+    let mut noisy = QuantumSimulator::new(num_qubits);
+    noisy.run_circuit_noisy(circuit, noise)?;
+
+    let fidelity = ideal
+        .get_state()
+        .hilbert_schmidt_inner_product(noisy.get_state())?
+        .re;
+    let dim = (1usize << num_qubits) as f64;
+    Ok((1.0 - fidelity) / (1.0 - 1.0 / dim))
+}
+
+/// Probability vector indexed by basis state from sampled bitstring counts
+/// Bitstrings put wire 0 first, matching `QuantumSimulator::probabilities`
+pub fn counts_to_probabilities(
+    counts: &HashMap<String, usize>,
+    num_qubits: usize,
+) -> Result<Vec<f64>, QsimError> {
+    counts_to_distribution(counts, num_qubits)
+}
+
+/// Bitstring counts as probabilities
+fn normalize_counts(counts: &HashMap<String, usize>) -> Result<HashMap<String, f64>, QsimError> {
+    let total: usize = counts.values().sum();
+    if total == 0 {
+        return Err(QsimError::InvalidParameter("Counts are empty".to_string()));
+    }
+    Ok(counts
+        .iter()
+        .map(|(bits, &c)| (bits.clone(), c as f64 / total as f64))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_custom_extrapolation_is_invoked() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        let at_smallest_scale = Extrapolation::Custom(Box::new(move |points: &[(f64, f64)]| {
+            counter.set(counter.get() + 1);
+            points
+                .iter()
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .map_or(f64::NAN, |&(_, value)| value)
+        }));
+
+        let value =
+            zero_noise_extrapolation(&[3.0, 1.0, 2.0], &[0.6, 0.8, 0.7], at_smallest_scale).unwrap();
+        assert_relative_eq!(value, 0.8);
+        assert_eq!(calls.get(), 1);
+
+        let custom = Extrapolation::Custom(Box::new(|_: &[(f64, f64)]| 0.0));
+        assert!(zero_noise_extrapolation(&[1.0, 2.0], &[0.5], custom).is_err());
+        let custom = Extrapolation::Custom(Box::new(|_: &[(f64, f64)]| 0.0));
+        assert!(zne_with_uncertainty(&[1.0, 2.0], &[0.5, 0.4], &[0.1, 0.1], custom).is_err());
+    }
+
+    #[test]
+    fn test_linear_extrapolation_recovers_intercept() {
+        let scales = [1.0, 2.0, 3.0];
+        let values: Vec<f64> = scales.iter().map(|s| 0.9 - 0.1 * s).collect();
+
+        let linear = zero_noise_extrapolation(&scales, &values, Extrapolation::Linear).unwrap();
+        let richardson =
+            zero_noise_extrapolation(&scales, &values, Extrapolation::Richardson).unwrap();
+
+        assert_relative_eq!(linear, 0.9, epsilon = 1e-10);
+        assert_relative_eq!(richardson, 0.9, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_uncertainty_grows_with_input_variance() {
+        let scales = [1.0, 2.0, 3.0];
+        let values = [0.8, 0.7, 0.6];
+
+        let (v_small, err_small) =
+            zne_with_uncertainty(&scales, &values, &[1e-4; 3], Extrapolation::Linear).unwrap();
+        let (v_large, err_large) =
+            zne_with_uncertainty(&scales, &values, &[1e-2; 3], Extrapolation::Linear).unwrap();
+
+        assert_relative_eq!(v_small, v_large, epsilon = 1e-12);
+        assert!(err_large > err_small);
+        // Same scale factors, so the error scales with sqrt of the variance ratio
+        assert_relative_eq!(err_large / err_small, 10.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_effective_channel_of_idle_qubit() {
+        use crate::density_matrix::DensityMatrix;
+        use crate::gates::{build_single_qubit_unitary, ry};
+        use crate::noise_model::{apply_amplitude_damping, apply_dephasing};
+
+        // Wire 0 sits idle for the single layer in which wire 1 is busy
+        let mut circuit = Circuit::new();
+        circuit.add_gate("Hadamard", &[1], &[]);
+        let noise = NoiseModel {
+            single_qubit_error: 0.05,
+            idle_gamma: 0.1,
+            idle_lambda: 0.05,
+            ..NoiseModel::noiseless()
+        };
+
+        let kraus = effective_single_qubit_channel(&circuit, &noise, 0).unwrap();
+
+        let mut extracted = DensityMatrix::new(1);
+        extracted.apply_unitary(&build_single_qubit_unitary(&ry(1.1), 0, 1));
+        let mut expected = extracted.clone();
+        extracted.apply_kraus(&kraus);
+        apply_amplitude_damping(&mut expected, 0, 0.1);
+        apply_dephasing(&mut expected, 0, 0.05);
+
+        assert_relative_eq!((extracted.matrix - expected.matrix).norm(), 0.0, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_fitted_noise_reproduces_target_fidelity() {
+        use crate::noise_model::{
+            amplitude_damping_kraus, average_gate_fidelity, dephasing_kraus, depolarizing_kraus,
+            global_depolarizing_kraus,
+        };
+
+        let target = 0.995;
+        let p1 = fit_depolarizing_to_fidelity(target, 1).unwrap();
+        assert_relative_eq!(average_gate_fidelity(&depolarizing_kraus(p1)), target, epsilon = 1e-12);
+
+        let p2 = fit_depolarizing_to_fidelity(target, 2).unwrap();
+        assert_relative_eq!(average_gate_fidelity(&global_depolarizing_kraus(p2, 2)), target, epsilon = 1e-12);
+
+        let gamma = fit_amplitude_damping_to_fidelity(target).unwrap();
+        assert_relative_eq!(average_gate_fidelity(&amplitude_damping_kraus(gamma)), target, epsilon = 1e-12);
+
+        let lambda = fit_dephasing_to_fidelity(target).unwrap();
+        assert_relative_eq!(average_gate_fidelity(&dephasing_kraus(lambda)), target, epsilon = 1e-12);
+
+        assert!(fit_amplitude_damping_to_fidelity(0.3).is_err());
+    }
+
+    #[test]
+    fn test_group_commuting() {
+        let terms = |strings: &[&str]| -> Vec<(f64, String)> {
+            strings.iter().map(|p| (1.0, p.to_string())).collect()
+        };
+
+        assert_eq!(group_commuting(&terms(&["ZI", "IZ", "ZZ"])), vec![vec![0, 1, 2]]);
+        assert_eq!(group_commuting(&terms(&["XI", "ZI"])), vec![vec![0], vec![1]]);
+        assert_eq!(
+            group_commuting(&terms(&["ZZ", "XX", "ZI", "IX", "YY"])),
+            vec![vec![0, 2], vec![1, 3], vec![4]]
+        );
+    }
+
+    #[test]
+    fn test_estimate_hamiltonian_sampled() {
+        let mut sim = QuantumSimulator::new(2);
+        sim.set_seed(5);
+        sim.apply_gate("H", &[0], &[]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+
+        let terms = vec![
+            (0.5, "ZZ".to_string()),
+            (0.3, "XX".to_string()),
+            (-0.4, "YY".to_string()),
+            (0.2, "ZI".to_string()),
+        ];
+        // Bell state: ⟨ZZ⟩ = ⟨XX⟩ = 1, ⟨YY⟩ = -1, ⟨ZI⟩ = 0
+        let estimate = estimate_hamiltonian_sampled(&sim, &terms, 20_000).unwrap();
+        assert!((estimate - 1.2).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_chsh_bell_state_violates_classical_bound() {
+        use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+        let angles = [0.0, FRAC_PI_2, FRAC_PI_4, -FRAC_PI_4];
+
+        let mut sim = QuantumSimulator::new(2);
+        sim.apply_gate("H", &[0], &[]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        assert_relative_eq!(chsh_value(&sim, 0, 1, angles).unwrap(), 2.0 * 2f64.sqrt(), epsilon = 1e-10);
+
+        // Strong depolarizing noise pushes S below the classical bound
+        sim.apply_depolarizing(0, 0.5);
+        sim.apply_depolarizing(1, 0.5);
+        assert!(chsh_value(&sim, 0, 1, angles).unwrap() < 2.0);
+        assert!(chsh_value(&sim, 0, 0, angles).is_err());
+    }
+
+    #[test]
+    fn test_channel_fixed_points() {
+        use crate::noise_model::ChannelKind;
+
+        // Amplitude damping relaxes the target to |0⟩ and leaves the other qubit mixed
+        let damping = Channel::from_kind(ChannelKind::AmplitudeDamping, 0.3);
+        let rho = channel_fixed_point(&damping, 1, 2, 500);
+        let target = rho.partial_trace(&[1]).unwrap();
+        assert_relative_eq!(target.matrix[(0, 0)].re, 1.0, epsilon = 1e-9);
+        assert_relative_eq!(target.matrix[(1, 1)].re, 0.0, epsilon = 1e-9);
+        assert_relative_eq!(rho.partial_trace(&[0]).unwrap().matrix[(0, 0)].re, 0.5, epsilon = 1e-12);
+
+        let depolarizing = Channel::from_kind(ChannelKind::Depolarizing, 0.2);
+        let rho = channel_fixed_point(&depolarizing, 0, 1, 500);
+        assert_relative_eq!(rho.matrix[(0, 0)].re, 0.5, epsilon = 1e-12);
+        assert_relative_eq!(rho.matrix[(0, 1)].norm(), 0.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_deeper_circuit_has_higher_error_estimate() {
+        let noise = NoiseModel {
+            single_qubit_error: 0.001,
+            two_qubit_error: 0.01,
+            idle_gamma: 0.0005,
+            idle_lambda: 0.0005,
+            ..NoiseModel::noiseless()
+        };
+
+        let mut shallow = Circuit::new();
+        shallow.add_gate("H", &[0], &[]).add_gate("CNOT", &[0, 1], &[]);
+        // One H, one CNOT and wire 1 idle during the first layer
+        assert_relative_eq!(estimate_circuit_error(&shallow, &noise), 0.012, epsilon = 1e-12);
+
+        let mut deep = shallow.clone();
+        deep.add_gate("RX", &[1], &[0.3])
+            .add_gate("CNOT", &[1, 0], &[])
+            .add_gate("RZ", &[0], &[0.1]);
+        assert!(estimate_circuit_error(&deep, &noise) > estimate_circuit_error(&shallow, &noise));
+
+        // A wire listed twice by one gate is still only one busy wire
+        let mut repeated = Circuit::new();
+        repeated.add_gate("SWAP", &[1, 1], &[]).add_gate("H", &[0], &[]);
+        assert_relative_eq!(estimate_circuit_error(&repeated, &noise), 0.011, epsilon = 1e-12);
+        repeated.add_gate("SWAP", &[1, 1], &[]);
+        assert_relative_eq!(estimate_circuit_error(&repeated, &noise), 0.022, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_compare_distributions_reports_improvement() {
+        let histogram = |entries: &[(&str, usize)]| -> HashMap<String, usize> {
+            entries.iter().map(|&(k, c)| (k.to_string(), c)).collect()
+        };
+        let ideal = histogram(&[("00", 500), ("11", 500)]);
+        let before = histogram(&[("00", 400), ("01", 100), ("10", 100), ("11", 400)]);
+        let after = histogram(&[("00", 470), ("01", 20), ("10", 30), ("11", 480)]);
+
+        let report = compare_distributions(&before, &after, &ideal).unwrap();
+        assert_relative_eq!(report.before_tvd, 0.2, epsilon = 1e-12);
+        assert_relative_eq!(report.after_tvd, 0.05, epsilon = 1e-12);
+        assert!(report.after_hellinger_fidelity > report.before_hellinger_fidelity);
+        assert_relative_eq!(report.before_hellinger_fidelity, 0.8, epsilon = 1e-12);
+        assert_relative_eq!(report.deltas["01"], -0.08, epsilon = 1e-12);
+        assert_relative_eq!(report.deltas["11"], 0.08, epsilon = 1e-12);
+
+        assert!(compare_distributions(&HashMap::new(), &after, &ideal).is_err());
+    }
+
+    #[test]
+    fn test_phase_estimation_of_z_rotation() {
+        // RZ(3π/2) has eigenvalues e^{∓3πi/4} on |0⟩ and |1⟩, i.e. phases 5/8 and 3/8
+        let mut unitary = Circuit::new();
+        unitary.add_gate("RZ", &[3], &[1.5 * std::f64::consts::PI]);
+
+        let mut sim = QuantumSimulator::new(4);
+        sim.apply_gate("PauliX", &[3], &[]).unwrap();
+        assert_eq!(phase_estimation(&mut sim, &unitary, &[0, 1, 2], &[3]).unwrap(), 3);
+
+        let mut sim = QuantumSimulator::new(4);
+        assert_eq!(phase_estimation(&mut sim, &unitary, &[0, 1, 2], &[3]).unwrap(), 5);
+
+        assert!(phase_estimation(&mut sim, &unitary, &[0, 1, 2], &[2, 3]).is_err());
+        assert!(phase_estimation(&mut sim, &unitary, &[0, 1], &[2]).is_err());
+    }
+
+    #[test]
+    fn test_transpose_channel_recovery_improves_fidelity() {
+        use crate::gates::kron;
+        use crate::noise_model::amplitude_damping_kraus;
+
+        // Amplitude damping on both qubits of the code span{|00⟩, |11⟩}
+        let single = amplitude_damping_kraus(0.2);
+        let noise = Channel::new(
+            single.iter().flat_map(|a| single.iter().map(move |b| kron(a, b))).collect(),
+        );
+        let mut projector = DMatrix::zeros(4, 4);
+        projector[(0, 0)] = Complex::new(1.0, 0.0);
+        projector[(3, 3)] = Complex::new(1.0, 0.0);
+
+        // Entanglement fidelity on the code: Σ |Tr(P A P)|² / d² over Kraus operators A
+        let code_fidelity = |kraus: &[DMatrix<Complex<f64>>]| {
+            kraus.iter().map(|a| (&projector * a * &projector).trace().norm_sqr()).sum::<f64>() / 4.0
+        };
+
+        let recovery = transpose_channel_recovery(&noise, &projector);
+        let completeness = recovery
+            .kraus
+            .iter()
+            .fold(DMatrix::<Complex<f64>>::zeros(4, 4), |acc, r| acc + r.adjoint() * r);
+        assert_relative_eq!((completeness - DMatrix::identity(4, 4)).norm(), 0.0, epsilon = 1e-10);
+
+        let recovered: Vec<DMatrix<Complex<f64>>> = recovery
+            .kraus
+            .iter()
+            .flat_map(|r| noise.kraus.iter().map(move |e| r * e))
+            .collect();
+        let bare = code_fidelity(&noise.kraus);
+        assert_relative_eq!(bare, 0.81, epsilon = 1e-12);
+        assert!(code_fidelity(&recovered) > bare);
+    }
+
+    #[test]
+    fn test_shots_for_precision_scales_quadratically() {
+        assert_eq!(shots_for_precision(1.0, 0.01).unwrap(), 10_000);
+        assert_eq!(shots_for_precision(1.0, 0.005).unwrap(), 40_000);
+        for variance in [0.3, 0.75] {
+            let coarse = shots_for_precision(variance, 0.02).unwrap();
+            let fine = shots_for_precision(variance, 0.01).unwrap();
+            assert!((fine as f64 / coarse as f64 - 4.0).abs() < 1e-3);
+        }
+
+        let mut sim = QuantumSimulator::new(1);
+        sim.apply_gate("RY", &[0], &[std::f64::consts::FRAC_PI_3]).unwrap();
+        let variance = sim.get_state().pauli_variance("Z").unwrap();
+        assert_relative_eq!(variance, 0.75, epsilon = 1e-12);
+        assert_eq!(shots_for_precision(variance, 0.01).unwrap(), 7_500);
+        assert!(shots_for_precision(1.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_error_attribution_flags_noisiest_qubit() {
+        let mut ghz = Circuit::new();
+        ghz.add_gate("Hadamard", &[0], &[]);
+        ghz.add_gate("CNOT", &[0, 1], &[]);
+        ghz.add_gate("CNOT", &[1, 2], &[]);
+
+        // Wire 1 runs six times noisier than its neighbours
+        let model = NoiseModel {
+            single_qubit_error: 0.01,
+            two_qubit_error: 0.02,
+            idle_gamma: 0.005,
+            idle_lambda: 0.005,
+            qubit_noise_scale: vec![1.0, 6.0, 1.0],
+            ..NoiseModel::noiseless()
+        };
+
+        let shares = error_attribution(&ghz, "XXX", &model).unwrap();
+        assert_eq!(shares.len(), 3);
+        assert!(shares[&1] > shares[&0] && shares[&1] > shares[&2], "{:?}", shares);
+        assert!(shares.values().all(|&s| s > 0.0 && s <= 1.0));
+
+        let quiet = error_attribution(&ghz, "XXX", &NoiseModel::noiseless()).unwrap();
+        assert!(quiet.values().all(|&s| s == 0.0));
+        assert!(error_attribution(&ghz, "XX", &model).is_err());
+    }
+
+    #[test]
+    fn test_counts_to_probabilities_round_trip() {
+        let mut sim = QuantumSimulator::new(3);
+        sim.apply_gate("RY", &[0], &[0.9]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        sim.apply_gate("RX", &[2], &[2.1]).unwrap();
+        let probs = sim.get_state().probabilities();
+
+        let n_shots = 100_000;
+        let counts: HashMap<String, usize> = sim
+            .exact_counts(n_shots)
+            .into_iter()
+            .map(|(bits, c)| (bits, c.round() as usize))
+            .collect();
+        let recovered = counts_to_probabilities(&counts, 3).unwrap();
+        assert_eq!(recovered.len(), 8);
+        for (r, p) in recovered.iter().zip(&probs) {
+            assert!((r - p).abs() < 1e-4, "{} vs {}", r, p);
+        }
+
+        sim.set_seed(5);
+        let sampled = counts_to_probabilities(&sim.measure_counts(n_shots), 3).unwrap();
+        for (s, p) in sampled.iter().zip(&probs) {
+            assert!((s - p).abs() < 0.01, "{} vs {}", s, p);
+        }
+
+        assert!(counts_to_probabilities(&HashMap::from([("01".to_string(), 3)]), 3).is_err());
+        assert!(counts_to_probabilities(&HashMap::new(), 3).is_err());
+    }
+
+    #[test]
+    fn test_gate_noise_impact_ranks_gates() {
+        let noise = NoiseModel {
+            single_qubit_error: 0.01,
+            two_qubit_error: 0.05,
+            ..NoiseModel::noiseless()
+        };
+
+        // Depolarizing p on one qubit has F_pro = 1 - 3p/4, so r = p/2
+        let single = gate_noise_impact("Hadamard", &[0], &[], &noise, 1).unwrap();
+        assert_relative_eq!(single, 0.005, epsilon = 1e-12);
+
+        let two = gate_noise_impact("CNOT", &[0, 1], &[], &noise, 2).unwrap();
+        let one_of_two = gate_noise_impact("RX", &[1], &[0.3], &noise, 2).unwrap();
+        assert!(two > one_of_two, "{} vs {}", two, one_of_two);
+
+        let ideal = gate_noise_impact("CNOT", &[0, 1], &[], &NoiseModel::noiseless(), 2).unwrap();
+        assert_relative_eq!(ideal, 0.0, epsilon = 1e-12);
+        assert!(gate_noise_impact("CNOT", &[0, 2], &[], &noise, 2).is_err());
+    }
+
+    #[test]
+    fn test_depolarizing_rescale_recovers_ideal() {
+        use crate::noise_model::global_depolarizing_kraus;
+
+        let mut sim = QuantumSimulator::new(2);
+        sim.apply_gate("RY", &[0], &[0.8]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        sim.apply_gate("RX", &[1], &[-0.5]).unwrap();
+        let ideal = sim.get_state().clone();
+
+        let p = 0.23;
+        let mut noisy = ideal.clone();
+        noisy.apply_kraus(&global_depolarizing_kraus(p, 2));
+        for pauli in ["ZZ", "XX", "ZI", "YZ"] {
+            let exact = ideal.expectation_pauli(pauli).unwrap();
+            let rescaled = depolarizing_rescale(noisy.expectation_pauli(pauli).unwrap(), p);
+            assert_relative_eq!(rescaled, exact, epsilon = 1e-12);
+        }
+
+        // On one qubit the local depolarizing gate error is a global depolarizing channel
+        let mut circuit = Circuit::new();
+        circuit.add_gate("RY", &[0], &[1.0]);
+        let noise = NoiseModel {
+            single_qubit_error: 0.1,
+            ..NoiseModel::noiseless()
+        };
+        let p_est = estimate_global_depolarizing(&circuit, &noise).unwrap();
+        assert_relative_eq!(p_est, 0.1, epsilon = 1e-12);
+
+        let mut noisy_sim = QuantumSimulator::new(1);
+        noisy_sim.run_circuit_noisy(&circuit, &noise).unwrap();
+        let rescaled = depolarizing_rescale(noisy_sim.expectation_pauli("Z").unwrap(), p_est);
+        assert_relative_eq!(rescaled, 1.0f64.cos(), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_total_variation_distance_bounds() {
+        let p = [0.1, 0.4, 0.3, 0.2];
+        assert_relative_eq!(total_variation_distance(&p, &p).unwrap(), 0.0);
+        assert_relative_eq!(
+            total_variation_distance(&[0.5, 0.5, 0.0, 0.0], &[0.0, 0.0, 0.25, 0.75]).unwrap(),
+            1.0,
+            epsilon = 1e-12
+        );
+        assert_relative_eq!(
+            total_variation_distance(&p, &[0.25; 4]).unwrap(),
+            0.2,
+            epsilon = 1e-12
+        );
+        assert!(total_variation_distance(&p, &[1.0]).is_err());
+    }
+
+    #[test]
+    fn test_repetition_code_suppresses_errors_with_distance() {
+        let p = 0.05;
+        assert_relative_eq!(repetition_code_logical_error(1, p, 1).unwrap(), p, epsilon = 1e-15);
+        // d = 3 fails when two or three bits flip: 3p²(1-p) + p³
+        let d3 = 3.0 * p * p * (1.0 - p) + p.powi(3);
+        assert_relative_eq!(repetition_code_logical_error(3, p, 1).unwrap(), d3, epsilon = 1e-15);
+
+        let rates: Vec<f64> = [1, 3, 5, 7]
+            .iter()
+            .map(|&d| repetition_code_logical_error(d, p, 10).unwrap())
+            .collect();
+        assert!(rates.windows(2).all(|pair| pair[1] < pair[0]), "{:?}", rates);
+
+        // Above threshold adding qubits makes things worse
+        let rate = |d| repetition_code_logical_error(d, 0.6, 1).unwrap();
+        assert!(rate(5) > rate(3));
+        assert_relative_eq!(
+            repetition_code_logical_error(4, 0.5, 3).unwrap(),
+            0.5,
+            epsilon = 1e-12
+        );
+        assert_eq!(repetition_code_logical_error(3, p, 0).unwrap(), 0.0);
+
+        // Errors accumulate over rounds towards the fully mixed value 1/2
+        let one = repetition_code_logical_error(3, p, 1).unwrap();
+        let two = repetition_code_logical_error(3, p, 2).unwrap();
+        assert_relative_eq!(two, 2.0 * one * (1.0 - one), epsilon = 1e-15);
+
+        assert!(repetition_code_logical_error(0, p, 1).is_err());
+        assert!(repetition_code_logical_error(3, -0.1, 1).is_err());
+        assert!(repetition_code_logical_error(3, 1.5, 1).is_err());
+        assert!(repetition_code_logical_error(3, f64::NAN, 1).is_err());
+    }
+
+    #[test]
+    fn test_zne_recovers_exact_polynomials() {
+        let scales = [1.0, 1.5, 2.0, 3.0];
+        let linear: Vec<f64> = scales.iter().map(|&l| 0.8 - 0.15 * l).collect();
+        assert_relative_eq!(zne_linear(&linear, &scales).unwrap(), 0.8, epsilon = 1e-9);
+
+        let quadratic: Vec<f64> = scales.iter().map(|&l| 0.9 - 0.2 * l + 0.03 * l * l).collect();
+        assert_relative_eq!(zne_quadratic(&quadratic, &scales).unwrap(), 0.9, epsilon = 1e-9);
+        // A straight line cannot follow the curvature
+        assert!((zne_linear(&quadratic, &scales).unwrap() - 0.9).abs() > 1e-3);
+
+        let mut calls = Vec::new();
+        let value = zne_from_fn(
+            &scales,
+            |l| {
+                calls.push(l);
+                0.9 - 0.2 * l + 0.03 * l * l
+            },
+            Extrapolation::Quadratic,
+        )
+        .unwrap();
+        assert_relative_eq!(value, 0.9, epsilon = 1e-9);
+        assert_eq!(calls, scales);
+
+        assert!(matches!(
+            zne_quadratic(&[0.7, 0.6], &[1.0, 2.0]),
+            Err(QsimError::InvalidParameter(msg)) if msg.contains("at least 3")
+        ));
+    }
+}
@@ -1,6 +1,8 @@
 use nalgebra::DMatrix;
 use num_complex::Complex;
 use crate::density_matrix::DensityMatrix;
+use crate::error::QsimError;
+use crate::readout::ReadoutError;
 
 /// Amplitude damping channel - models energy relaxation (T1 decay)
 /// Describes decay from |1⟩ to |0⟩ with probability gamma
@@ -41,6 +43,34 @@ pub fn dephasing_kraus(lambda: f64) -> Vec<DMatrix<Complex<f64>>> {
     vec![k0, k1]
 }
 
+/// Pauli axis of a single qubit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// Dephasing along an arbitrary Pauli axis
+/// Conjugates the Z-basis dephasing Kraus operators by the basis change U with U Z U† = P:
+/// U = H for X and U = RX(-π/2) for Y. Components of the Bloch vector orthogonal to
+/// `axis` are scaled by (1 - 2 lambda) while the component along `axis` is preserved
+pub fn dephasing_kraus_basis(lambda: f64, axis: Axis) -> Vec<DMatrix<Complex<f64>>> {
+    use crate::gates::{hadamard, rx};
+
+    let z_kraus = dephasing_kraus(lambda);
+    let basis_change = match axis {
+        Axis::X => hadamard(),
+        Axis::Y => rx(-std::f64::consts::FRAC_PI_2),
+        Axis::Z => return z_kraus,
+    };
+
+    z_kraus
+        .iter()
+        .map(|k| &basis_change * k * basis_change.adjoint())
+        .collect()
+}
+
 /// Depolarizing channel - models symmetric contraction of the Bloch sphere
 /// rho -> (1-p)rho + p I/2
 pub fn depolarizing_kraus(p: f64) -> Vec<DMatrix<Complex<f64>>> {
@@ -78,6 +108,248 @@ pub fn depolarizing_kraus(p: f64) -> Vec<DMatrix<Complex<f64>>> {
     vec![k0, k1, k2, k3]
 }
 
+/// Single-qubit noise channel families parameterized by a single strength
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelKind {
+    /// Energy relaxation (T1), strength gamma
+    AmplitudeDamping,
+    /// Loss of coherence without energy loss (T2), strength lambda
+    Dephasing,
+    /// Symmetric contraction of the Bloch sphere, strength p
+    Depolarizing,
+}
+
+impl ChannelKind {
+    /// Single-qubit Kraus operators of this channel at the given strength
+    pub fn kraus(&self, param: f64) -> Vec<DMatrix<Complex<f64>>> {
+        match self {
+            ChannelKind::AmplitudeDamping => amplitude_damping_kraus(param),
+            ChannelKind::Dephasing => dephasing_kraus(param),
+            ChannelKind::Depolarizing => depolarizing_kraus(param),
+        }
+    }
+}
+
+/// A channel given by its Kraus operators (2x2 for a single-qubit channel)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+    pub kraus: Vec<DMatrix<Complex<f64>>>,
+}
+
+impl Channel {
+    pub fn new(kraus: Vec<DMatrix<Complex<f64>>>) -> Self {
+        Channel { kraus }
+    }
+
+    /// Channel of a standard family at the given strength
+    pub fn from_kind(kind: ChannelKind, param: f64) -> Self {
+        Channel::new(kind.kraus(param))
+    }
+
+    /// Apply a single-qubit channel to a specific qubit wire
+    pub fn apply(&self, rho: &mut DensityMatrix, wire: usize) {
+        let full_kraus = expand_kraus_to_full_system(&self.kraus, wire, rho.num_qubits);
+        rho.apply_kraus(&full_kraus);
+    }
+}
+
+/// Fixed point of zero-temperature amplitude damping on one qubit: |0⟩⟨0|
+pub fn amplitude_damping_steady_state() -> DensityMatrix {
+    DensityMatrix::new(1)
+}
+
+/// Check whether applying a single-qubit channel to every wire leaves ρ unchanged
+/// (Frobenius distance below 1e-10)
+pub fn is_steady_state(rho: &DensityMatrix, channel: &Channel) -> bool {
+    let mut evolved = rho.clone();
+    for wire in 0..rho.num_qubits {
+        channel.apply(&mut evolved, wire);
+    }
+    (&evolved.matrix - &rho.matrix).norm() < 1e-10
+}
+
+/// Amplitude damping and dephasing strengths for relaxing over `time` with the given T1, T2
+/// gamma = 1 - e^(-t/T1); pure dephasing at rate 1/T2 - 1/(2 T1) gives λ = (1 - e^(-t/Tφ)) / 2,
+/// so coherences decay as e^(-t/T2) overall. T2 > 2 T1 is unphysical and treated as T2 = 2 T1.
+pub fn relaxation_strengths(t1: f64, t2: f64, time: f64) -> (f64, f64) {
+    let pure_dephasing_rate = (1.0 / t2 - 0.5 / t1).max(0.0);
+    let gamma = 1.0 - (-time / t1).exp();
+    let lambda = 0.5 * (1.0 - (-time * pure_dephasing_rate).exp());
+    (gamma, lambda)
+}
+
+/// Exact zero-temperature thermal-relaxation channel over `gate_time`
+///
+/// T1 decay and T2 dephasing act simultaneously: the |1⟩ population decays as e^(-t/T1)
+/// while coherences decay as e^(-t/T2), realised directly by K0 = diag(1, e^(-t/T2)),
+/// K1 = √(1 - e^(-t/T1)) |0⟩⟨1| and K2 = √(e^(-t/T1) - e^(-2t/T2)) |1⟩⟨1|. Chaining
+/// amplitude damping with a dephasing channel at the full 1/T2 rate instead counts the
+/// T1 share of the coherence loss twice. At T2 = 2 T1, K2 vanishes and this is pure
+/// amplitude damping; T2 > 2 T1 is unphysical and treated as T2 = 2 T1.
+pub fn combined_thermal_kraus(t1: f64, t2: f64, gate_time: f64) -> Vec<DMatrix<Complex<f64>>> {
+    let population = (-gate_time / t1).exp();
+    let coherence = (-gate_time / t2.min(2.0 * t1)).exp();
+    relaxation_kraus(population, coherence)
+}
+
+/// Kraus operators scaling the |1⟩ population by `population` and coherences by `coherence`
+/// (physical when coherence² ≤ population)
+fn relaxation_kraus(population: f64, coherence: f64) -> Vec<DMatrix<Complex<f64>>> {
+    let zero = Complex::new(0.0, 0.0);
+    let k0 = DMatrix::from_row_slice(2, 2, &[
+        Complex::new(1.0, 0.0), zero,
+        zero, Complex::new(coherence, 0.0),
+    ]);
+    let k1 = DMatrix::from_row_slice(2, 2, &[
+        zero, Complex::new((1.0 - population).sqrt(), 0.0),
+        zero, zero,
+    ]);
+    let k2 = DMatrix::from_row_slice(2, 2, &[
+        zero, zero,
+        zero, Complex::new((population - coherence * coherence).max(0.0).sqrt(), 0.0),
+    ]);
+    vec![k0, k1, k2]
+}
+
+/// Zero-temperature thermal relaxation of a qubit over `time`, applied as the single
+/// combined channel of `combined_thermal_kraus`
+pub fn apply_thermal_relaxation(rho: &mut DensityMatrix, wire: usize, t1: f64, t2: f64, time: f64) {
+    let kraus = combined_thermal_kraus(t1, t2, time);
+    rho.apply_kraus(&expand_kraus_to_full_system(&kraus, wire, rho.num_qubits));
+}
+
+/// Global depolarizing channel on `num_qubits` qubits: ρ → (1-p)ρ + p I/d
+/// Kraus form: sqrt(1 - p + p/d²) I plus sqrt(p/d²) P for every non-identity Pauli string P
+pub fn global_depolarizing_kraus(p: f64, num_qubits: usize) -> Vec<DMatrix<Complex<f64>>> {
+    use crate::gates::{identity, kron, pauli_x, pauli_y, pauli_z};
+
+    let d2 = (1usize << (2 * num_qubits)) as f64;
+    let singles = [identity(), pauli_x(), pauli_y(), pauli_z()];
+
+    (0..1usize << (2 * num_qubits))
+        .map(|code| {
+            // Base-4 digits of `code` pick the Pauli on each wire; code 0 is the identity
+            let pauli = (0..num_qubits).fold(DMatrix::from_element(1, 1, Complex::new(1.0, 0.0)), |acc, w| {
+                kron(&acc, &singles[(code >> (2 * (num_qubits - 1 - w))) & 3])
+            });
+            let weight = if code == 0 { 1.0 - p + p / d2 } else { p / d2 };
+            pauli * Complex::new(weight.sqrt(), 0.0)
+        })
+        .collect()
+}
+
+/// Average gate fidelity of a channel to the identity: F = (Σᵢ |Tr Kᵢ|² + d) / (d(d + 1))
+pub fn average_gate_fidelity(kraus: &[DMatrix<Complex<f64>>]) -> f64 {
+    let d = kraus.first().map_or(1, |k| k.nrows()) as f64;
+    let overlap: f64 = kraus.iter().map(|k| k.trace().norm_sqr()).sum();
+    (overlap + d) / (d * (d + 1.0))
+}
+
+/// Superoperator of a channel acting on column-stacked density matrices
+/// vec(K ρ K†) = (K̄ ⊗ K) vec(ρ), so S = Σᵢ K̄ᵢ ⊗ Kᵢ
+pub fn superoperator(kraus: &[DMatrix<Complex<f64>>]) -> DMatrix<Complex<f64>> {
+    use crate::gates::kron;
+
+    let d = kraus.first().map_or(1, |k| k.nrows());
+    kraus
+        .iter()
+        .fold(DMatrix::zeros(d * d, d * d), |acc, k| acc + kron(&k.conjugate(), k))
+}
+
+/// Generator L = log(S) of a channel's superoperator S, so that exp(t·L) applies a
+/// fraction t of the noise (t = 1 recovers the channel itself)
+///
+/// Uses the principal matrix logarithm (inverse scaling and squaring). It only exists
+/// when S is invertible, and is only the "right" generator when no eigenvalue of S lies
+/// on or near the negative real axis: channels with negative eigenvalues (e.g. a bit flip
+/// with p > 1/2) sit across the branch cut and have no Markovian generator, in which case
+/// the result is still a logarithm but exp(t·L) need not be completely positive for t < 1.
+pub fn lindblad_generator(kraus: &[DMatrix<Complex<f64>>]) -> Result<DMatrix<Complex<f64>>, QsimError> {
+    matrix_log(&superoperator(kraus))
+}
+
+/// Superoperator exp(t·L) of a fraction `t` of the channel with generator L
+pub fn fractional_superoperator(generator: &DMatrix<Complex<f64>>, t: f64) -> DMatrix<Complex<f64>> {
+    (generator * Complex::new(t, 0.0)).exp()
+}
+
+/// Apply a superoperator acting on the whole register (dimension 4ⁿ x 4ⁿ)
+pub fn apply_superoperator(rho: &mut DensityMatrix, superop: &DMatrix<Complex<f64>>) {
+    let dim = rho.dim();
+    assert_eq!(superop.nrows(), dim * dim, "Superoperator dimension does not match the register");
+    let vectorized = DMatrix::from_column_slice(dim * dim, 1, rho.matrix.as_slice());
+    let mapped = superop * vectorized;
+    rho.matrix = DMatrix::from_column_slice(dim, dim, mapped.as_slice());
+}
+
+/// Principal matrix logarithm: take square roots until A is close to I, then sum the
+/// series log(I + X) = X - X²/2 + X³/3 - ... and scale back up by 2^k
+fn matrix_log(a: &DMatrix<Complex<f64>>) -> Result<DMatrix<Complex<f64>>, QsimError> {
+    let n = a.nrows();
+    let identity = DMatrix::<Complex<f64>>::identity(n, n);
+
+    let mut root = a.clone();
+    let mut squarings = 0;
+    while (&root - &identity).norm() > 0.25 {
+        if squarings == 60 {
+            return Err(QsimError::InvalidParameter("Matrix logarithm did not converge".to_string()));
+        }
+        root = matrix_sqrt(&root)?;
+        squarings += 1;
+    }
+
+    let x = &root - &identity;
+    let mut power = x.clone();
+    let mut log = DMatrix::zeros(n, n);
+    for j in 1..=60 {
+        let sign = if j % 2 == 1 { 1.0 } else { -1.0 };
+        log += &power * Complex::new(sign / j as f64, 0.0);
+        power = &power * &x;
+    }
+
+    Ok(log * Complex::new(2f64.powi(squarings), 0.0))
+}
+
+/// Principal matrix square root by the Denman–Beavers iteration
+fn matrix_sqrt(a: &DMatrix<Complex<f64>>) -> Result<DMatrix<Complex<f64>>, QsimError> {
+    let singular = || {
+        QsimError::InvalidParameter(
+            "Matrix logarithm is undefined for a singular superoperator".to_string(),
+        )
+    };
+    let half = Complex::new(0.5, 0.0);
+    let mut y = a.clone();
+    let mut z = DMatrix::identity(a.nrows(), a.ncols());
+
+    for _ in 0..100 {
+        let y_inv = y.clone().try_inverse().ok_or_else(singular)?;
+        let z_inv = z.clone().try_inverse().ok_or_else(singular)?;
+        let next = (&y + z_inv) * half;
+        z = (&z + y_inv) * half;
+        let change = (&next - &y).norm();
+        y = next;
+        if change < 1e-14 * y.norm() {
+            break;
+        }
+    }
+
+    Ok(y)
+}
+
+/// Apply a single-qubit channel of the given kind to a specific qubit wire
+pub fn apply_channel(
+    rho: &mut DensityMatrix,
+    wire: usize,
+    kind: ChannelKind,
+    param: f64,
+) {
+    match kind {
+        ChannelKind::AmplitudeDamping => apply_amplitude_damping(rho, wire, param),
+        ChannelKind::Dephasing => apply_dephasing(rho, wire, param),
+        ChannelKind::Depolarizing => apply_depolarizing(rho, wire, param),
+    }
+}
+
 /// Apply depolarizing noise to a specific qubit wire
 pub fn apply_depolarizing(
     rho: &mut DensityMatrix,
@@ -123,10 +395,67 @@ pub fn apply_dephasing(
     rho.apply_kraus(&full_kraus);
 }
 
-/// Apply idle noise to a qubit - combines amplitude damping and dephasing
+/// Apply dephasing along the given Pauli axis to a specific qubit wire
+pub fn apply_dephasing_basis(
+    rho: &mut DensityMatrix,
+    wire: usize,
+    lambda: f64,
+    axis: Axis,
+) {
+    if lambda <= 0.0 {
+        return; // No noise
+    }
+    
+    let single_qubit_kraus = dephasing_kraus_basis(lambda, axis);
+    let full_kraus = expand_kraus_to_full_system(&single_qubit_kraus, wire, rho.num_qubits);
+    rho.apply_kraus(&full_kraus);
+}
+
+/// Collective dephasing channel - the same random Z error hits all listed wires at once
+/// K0 = sqrt(1-lambda) I, K1 = sqrt(lambda) Z⊗Z⊗...⊗Z on `wires`
+pub fn collective_dephasing_kraus(
+    lambda: f64,
+    wires: &[usize],
+    num_qubits: usize,
+) -> Vec<DMatrix<Complex<f64>>> {
+    let dim = 1 << num_qubits;
+    let mask = wires
+        .iter()
+        .fold(0usize, |m, &w| m | (1 << (num_qubits - 1 - w)));
+
+    let k0 = DMatrix::from_diagonal_element(dim, dim, Complex::new((1.0 - lambda).sqrt(), 0.0));
+    let k1 = DMatrix::from_fn(dim, dim, |i, j| {
+        if i != j {
+            Complex::new(0.0, 0.0)
+        } else if (i & mask).count_ones() % 2 == 0 {
+            Complex::new(lambda.sqrt(), 0.0)
+        } else {
+            Complex::new(-lambda.sqrt(), 0.0)
+        }
+    });
+
+    vec![k0, k1]
+}
+
+/// Apply collective dephasing noise to a group of wires
+pub fn apply_collective_dephasing(
+    rho: &mut DensityMatrix,
+    wires: &[usize],
+    lambda: f64,
+) {
+    if lambda <= 0.0 {
+        return; // No noise
+    }
+
+    let kraus = collective_dephasing_kraus(lambda, wires, rho.num_qubits);
+    rho.apply_kraus(&kraus);
+}
+
+/// Apply idle noise to a qubit - amplitude damping and dephasing as one simultaneous
+/// relaxation channel (see `combined_thermal_kraus`)
 /// Protected flag determines noise strength:
-/// - protected = true: gamma = 0.001 (DD-protected, 100x reduction)
-/// - protected = false: gamma = 0.05 (unprotected idle)
+/// - protected = true: gamma = 0.01, lambda = 0.004 (DD-protected, 5x reduction)
+/// - protected = false: gamma = 0.05, lambda = 0.02 (unprotected idle)
 pub fn apply_idle_noise(
     rho: &mut DensityMatrix,
     wire: usize,
@@ -142,8 +471,150 @@ pub fn apply_idle_noise(
         0.02 * suppression_factor   // T2 noise
     );
     
-    apply_amplitude_damping(rho, wire, gamma);
-    apply_dephasing(rho, wire, lambda);
+    let kraus = relaxation_kraus(1.0 - gamma, (1.0 - gamma).sqrt() * (1.0 - 2.0 * lambda));
+    rho.apply_kraus(&expand_kraus_to_full_system(&kraus, wire, rho.num_qubits));
+}
+
+/// Where a gate's error channel is applied relative to the ideal gate
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoisePlacement {
+    /// Error channel, then the gate
+    Before,
+    /// The gate, then the error channel
+    #[default]
+    After,
+    /// Error channel on both sides of the gate
+    Both,
+}
+
+impl NoisePlacement {
+    /// Whether the error channel is applied before the gate
+    pub fn before(self) -> bool {
+        matches!(self, NoisePlacement::Before | NoisePlacement::Both)
+    }
+
+    /// Whether the error channel is applied after the gate
+    pub fn after(self) -> bool {
+        matches!(self, NoisePlacement::After | NoisePlacement::Both)
+    }
+}
+
+/// Gate-level noise model applied when running circuits
+/// Gate errors hit the wires a gate acts on; idle noise hits every other wire once per layer
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NoiseModel {
+    /// Depolarizing probability on the wire of a single-qubit gate
+    pub single_qubit_error: f64,
+    /// Depolarizing probability on each wire of a multi-qubit gate
+    pub two_qubit_error: f64,
+    /// Amplitude damping strength on each idle wire per layer (T1)
+    pub idle_gamma: f64,
+    /// Dephasing strength on each idle wire per layer (T2)
+    pub idle_lambda: f64,
+    /// Whether gate errors act before, after or on both sides of the gate
+    pub placement: NoisePlacement,
+    /// Bit-flip probabilities at measurement
+    pub readout: ReadoutError,
+    /// Per-wire (T1, T2); when set, every gate relaxes its wires for `gate_time`
+    pub relaxation_times: Vec<(f64, f64)>,
+    /// Duration of one gate, in the same units as `relaxation_times`
+    pub gate_time: f64,
+    /// Per-wire multiplier on every error rate (wires past the end use 1.0; 0.0 silences a wire)
+    pub qubit_noise_scale: Vec<f64>,
+}
+
+impl NoiseModel {
+    /// Noise model with every error rate set to zero
+    pub fn noiseless() -> Self {
+        NoiseModel::default()
+    }
+
+    /// Typical parameters for a named device archetype:
+    /// "superconducting_typical" or "ion_trap_typical"
+    pub fn preset(name: &str) -> Result<NoiseModel, QsimError> {
+        match name {
+            // Transmons: T1 = 100 µs, T2 = 80 µs, 50 ns layers
+            "superconducting_typical" => Ok(NoiseModel::from_device(
+                100e-6,
+                80e-6,
+                50e-9,
+                1e-3,
+                1e-2,
+                ReadoutError::new(0.01, 0.03),
+            )),
+            // Trapped ions: T1 ≈ 10 s (metastable), T2 = 1 s, 100 µs layers
+            "ion_trap_typical" => Ok(NoiseModel::from_device(
+                10.0,
+                1.0,
+                100e-6,
+                1e-4,
+                3e-3,
+                ReadoutError::new(0.003, 0.003),
+            )),
+            _ => Err(QsimError::InvalidParameter(format!(
+                "Unknown noise preset '{}'; expected superconducting_typical or ion_trap_typical",
+                name
+            ))),
+        }
+    }
+
+    /// Model whose idle strengths per layer come from coherence times (seconds)
+    fn from_device(
+        t1: f64,
+        t2: f64,
+        layer_time: f64,
+        single_qubit_error: f64,
+        two_qubit_error: f64,
+        readout: ReadoutError,
+    ) -> NoiseModel {
+        let (idle_gamma, idle_lambda) = relaxation_strengths(t1, t2, layer_time);
+        NoiseModel {
+            single_qubit_error,
+            two_qubit_error,
+            idle_gamma,
+            idle_lambda,
+            readout,
+            ..NoiseModel::default()
+        }
+    }
+
+    /// Apply the gate error for a gate that acted on `wires`
+    pub fn apply_gate_noise(&self, rho: &mut DensityMatrix, wires: &[usize]) {
+        let p = if wires.len() > 1 {
+            self.two_qubit_error
+        } else {
+            self.single_qubit_error
+        };
+        for &wire in wires {
+            let scale = self.wire_scale(wire);
+            apply_depolarizing(rho, wire, (p * scale).min(1.0));
+            if let Some(&(t1, t2)) = self.relaxation_times.get(wire) {
+                if scale > 0.0 {
+                    apply_thermal_relaxation(rho, wire, t1, t2, self.gate_time * scale);
+                }
+            }
+        }
+    }
+
+    /// Apply one layer's worth of idle noise to a wire
+    /// Damping and dephasing act as one simultaneous relaxation channel (see
+    /// `combined_thermal_kraus`) that keeps (1 - γ) of the |1⟩ population and
+    /// √(1 - γ)(1 - 2λ) of the coherence
+    pub fn apply_idle_noise(&self, rho: &mut DensityMatrix, wire: usize) {
+        let scale = self.wire_scale(wire);
+        let gamma = (self.idle_gamma * scale).min(1.0);
+        let lambda = (self.idle_lambda * scale).min(1.0);
+        if gamma <= 0.0 && lambda <= 0.0 {
+            return;
+        }
+        let kraus = relaxation_kraus(1.0 - gamma, (1.0 - gamma).sqrt() * (1.0 - 2.0 * lambda));
+        rho.apply_kraus(&expand_kraus_to_full_system(&kraus, wire, rho.num_qubits));
+    }
+
+    /// Error-rate multiplier for one wire
+    pub fn wire_scale(&self, wire: usize) -> f64 {
+        self.qubit_noise_scale.get(wire).copied().unwrap_or(1.0)
+    }
 }
 
 /// Expand single-qubit Kraus operators to full multi-qubit system
@@ -204,4 +675,177 @@ mod tests {
         
         assert_relative_eq!(trace_before, trace_after, epsilon = 1e-10);
     }
+
+    #[test]
+    fn test_x_basis_dephasing_preserves_only_x() {
+        use crate::gates::{build_single_qubit_unitary, ry, rz};
+
+        let mut rho = DensityMatrix::new(1);
+        rho.apply_unitary(&build_single_qubit_unitary(&ry(1.0), 0, 1));
+        rho.apply_unitary(&build_single_qubit_unitary(&rz(0.7), 0, 1));
+        let before = ["X", "Y", "Z"].map(|p| rho.expectation_pauli(p).unwrap());
+        assert!(before.iter().all(|v| v.abs() > 0.1));
+
+        apply_dephasing_basis(&mut rho, 0, 0.5, Axis::X);
+        assert_relative_eq!(rho.expectation_pauli("X").unwrap(), before[0], epsilon = 1e-10);
+        assert_relative_eq!(rho.expectation_pauli("Y").unwrap(), 0.0, epsilon = 1e-10);
+        assert_relative_eq!(rho.expectation_pauli("Z").unwrap(), 0.0, epsilon = 1e-10);
+
+        // Y-basis dephasing at partial strength shrinks X and Z by (1 - 2λ)
+        let mut rho_y = DensityMatrix::new(1);
+        rho_y.apply_unitary(&build_single_qubit_unitary(&ry(1.0), 0, 1));
+        rho_y.apply_unitary(&build_single_qubit_unitary(&rz(0.7), 0, 1));
+        apply_dephasing_basis(&mut rho_y, 0, 0.2, Axis::Y);
+        assert_relative_eq!(rho_y.expectation_pauli("Y").unwrap(), before[1], epsilon = 1e-10);
+        assert_relative_eq!(rho_y.expectation_pauli("X").unwrap(), 0.6 * before[0], epsilon = 1e-10);
+        assert_relative_eq!(rho_y.expectation_pauli("Z").unwrap(), 0.6 * before[2], epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_half_generator_composes_to_channel() {
+        let kraus = amplitude_damping_kraus(0.3);
+        let channel = superoperator(&kraus);
+        let generator = lindblad_generator(&kraus).unwrap();
+
+        let half = fractional_superoperator(&generator, 0.5);
+        assert_relative_eq!((&half * &half - &channel).norm(), 0.0, epsilon = 1e-10);
+        assert_relative_eq!((fractional_superoperator(&generator, 1.0) - &channel).norm(), 0.0, epsilon = 1e-10);
+
+        // Two half-steps on a state agree with one application of the Kraus channel
+        let mut rho = DensityMatrix::new(1);
+        rho.matrix = DMatrix::from_row_slice(2, 2, &[
+            Complex::new(0.3, 0.0), Complex::new(0.2, -0.1),
+            Complex::new(0.2, 0.1), Complex::new(0.7, 0.0),
+        ]);
+        let mut expected = rho.clone();
+        expected.apply_kraus(&kraus);
+        apply_superoperator(&mut rho, &half);
+        apply_superoperator(&mut rho, &half);
+        assert_relative_eq!((&rho.matrix - &expected.matrix).norm(), 0.0, epsilon = 1e-10);
+
+        assert!(lindblad_generator(&amplitude_damping_kraus(1.0)).is_err());
+    }
+
+    #[test]
+    fn test_amplitude_damping_relaxes_to_steady_state() {
+        use crate::gates::{build_single_qubit_unitary, ry, rz};
+
+        let damping = Channel::from_kind(ChannelKind::AmplitudeDamping, 0.2);
+        let steady = amplitude_damping_steady_state();
+        assert!(is_steady_state(&steady, &damping));
+
+        let mut rho = DensityMatrix::new(1);
+        rho.apply_unitary(&build_single_qubit_unitary(&ry(2.3), 0, 1));
+        rho.apply_unitary(&build_single_qubit_unitary(&rz(0.9), 0, 1));
+        assert!(!is_steady_state(&rho, &damping));
+
+        let mut previous = rho.trace_distance(&steady).unwrap();
+        for _ in 0..100 {
+            damping.apply(&mut rho, 0);
+            let distance = rho.trace_distance(&steady).unwrap();
+            assert!(distance <= previous + 1e-15);
+            previous = distance;
+        }
+        assert!(previous < 1e-4);
+        assert!(!is_steady_state(&steady, &Channel::from_kind(ChannelKind::Depolarizing, 0.1)));
+    }
+
+    #[test]
+    fn test_device_presets() {
+        for name in ["superconducting_typical", "ion_trap_typical"] {
+            let noise = NoiseModel::preset(name).unwrap();
+            for rate in [noise.single_qubit_error, noise.two_qubit_error, noise.idle_gamma, noise.idle_lambda] {
+                assert!(rate > 0.0 && rate < 0.05, "{}: {}", name, rate);
+            }
+            assert!(noise.readout.p01 > 0.0 && noise.readout.p10 < 0.5);
+        }
+
+        let superconducting = NoiseModel::preset("superconducting_typical").unwrap();
+        let ion_trap = NoiseModel::preset("ion_trap_typical").unwrap();
+        assert!(ion_trap.two_qubit_error < superconducting.two_qubit_error);
+        assert_relative_eq!(superconducting.idle_gamma, 1.0 - (-5e-4f64).exp(), epsilon = 1e-15);
+        assert!(NoiseModel::preset("photonic").is_err());
+    }
+
+    #[test]
+    fn test_thermal_relaxation_after_gates_decays_with_depth() {
+        use crate::circuit::Circuit;
+        use crate::simulator::QuantumSimulator;
+
+        let (t1, t2, gate_time) = (50.0, 30.0, 1.0);
+        let noise = NoiseModel {
+            relaxation_times: vec![(t1, t2)],
+            gate_time,
+            ..NoiseModel::noiseless()
+        };
+
+        let mut previous = (1.0, 1.0);
+        for depth in 0..6 {
+            // Excited population decays with T1 ...
+            let mut circuit = Circuit::new();
+            circuit.add_gate("PauliX", &[0], &[]);
+            for _ in 0..depth {
+                circuit.add_gate("RZ", &[0], &[0.0]);
+            }
+            let mut sim = QuantumSimulator::new(1);
+            sim.run_circuit_noisy(&circuit, &noise).unwrap();
+            let elapsed = (depth + 1) as f64 * gate_time;
+            let excited = 0.5 * (1.0 - sim.expectation_pauli("Z").unwrap());
+            assert_relative_eq!(excited, (-elapsed / t1).exp(), epsilon = 1e-12);
+
+            // ... and coherence with T2
+            let mut circuit = Circuit::new();
+            circuit.add_gate("Hadamard", &[0], &[]);
+            for _ in 0..depth {
+                circuit.add_gate("RZ", &[0], &[0.0]);
+            }
+            let mut sim = QuantumSimulator::new(1);
+            sim.run_circuit_noisy(&circuit, &noise).unwrap();
+            let coherence = sim.expectation_pauli("X").unwrap();
+            assert_relative_eq!(coherence, (-elapsed / t2).exp(), epsilon = 1e-12);
+
+            assert!(excited < previous.0 && coherence < previous.1);
+            previous = (excited, coherence);
+        }
+    }
+
+    #[test]
+    fn test_combined_thermal_kraus_vs_sequential() {
+        let (t1, time) = (1.0, 0.3);
+
+        // T2 = 2 T1: no pure dephasing, so the channel is amplitude damping
+        let combined = superoperator(&combined_thermal_kraus(t1, 2.0 * t1, time));
+        let damping = superoperator(&amplitude_damping_kraus(1.0 - (-time / t1).exp()));
+        assert_relative_eq!((combined - damping).norm(), 0.0, epsilon = 1e-12);
+
+        // Shorter T2: the coherence of |+⟩ decays exactly as e^(-t/T2)
+        let t2 = 0.5 * t1;
+        let plus = |rho: &mut DensityMatrix| {
+            rho.matrix.fill(Complex::new(0.5, 0.0));
+        };
+        let mut exact = DensityMatrix::new(1);
+        plus(&mut exact);
+        exact.apply_kraus(&combined_thermal_kraus(t1, t2, time));
+        assert_relative_eq!(exact.matrix[(0, 1)].re, 0.5 * (-time / t2).exp(), epsilon = 1e-12);
+
+        // Damping followed by dephasing at the full 1/T2 rate over-counts the T1 share
+        let mut sequential = DensityMatrix::new(1);
+        plus(&mut sequential);
+        apply_amplitude_damping(&mut sequential, 0, 1.0 - (-time / t1).exp());
+        apply_dephasing(&mut sequential, 0, 0.5 * (1.0 - (-time / t2).exp()));
+        assert!((exact.matrix[(0, 1)] - sequential.matrix[(0, 1)]).norm() > 0.02);
+        assert_relative_eq!(exact.matrix[(1, 1)].re, sequential.matrix[(1, 1)].re, epsilon = 1e-12);
+
+        // NoiseModel idle noise with the strengths from `relaxation_strengths` agrees
+        let (idle_gamma, idle_lambda) = relaxation_strengths(t1, t2, time);
+        let noise = NoiseModel {
+            idle_gamma,
+            idle_lambda,
+            ..NoiseModel::noiseless()
+        };
+        let mut idle = DensityMatrix::new(1);
+        plus(&mut idle);
+        noise.apply_idle_noise(&mut idle, 0);
+        assert_relative_eq!((&idle.matrix - &exact.matrix).norm(), 0.0, epsilon = 1e-12);
+    }
 }
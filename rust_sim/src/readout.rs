@@ -0,0 +1,292 @@
+use crate::error::QsimError;
+use nalgebra::{DMatrix, DVector};
+use rand::Rng;
+use std::collections::HashMap;
+
+/// Classical readout error: each measured bit is flipped independently
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReadoutError {
+    /// Probability of reading 1 when the qubit is in |0⟩
+    pub p01: f64,
+    /// Probability of reading 0 when the qubit is in |1⟩
+    pub p10: f64,
+}
+
+impl ReadoutError {
+    pub fn new(p01: f64, p10: f64) -> Self {
+        ReadoutError { p01, p10 }
+    }
+
+    /// Flip the bits of a measured bitstring according to the error rates
+    pub fn corrupt<R: Rng>(&self, bits: &mut [usize], rng: &mut R) {
+        for bit in bits.iter_mut() {
+            let flip_prob = if *bit == 0 { self.p01 } else { self.p10 };
+            if rng.gen::<f64>() < flip_prob {
+                *bit ^= 1;
+            }
+        }
+    }
+
+    /// Confusion matrix M[(measured, true)] = P(measured | true) for `num_qubits` qubits
+    /// Built as the tensor product of identical single-qubit confusion matrices
+    pub fn confusion_matrix(&self, num_qubits: usize) -> DMatrix<f64> {
+        let single = DMatrix::from_row_slice(2, 2, &[
+            1.0 - self.p01, self.p10,
+            self.p01, 1.0 - self.p10,
+        ]);
+
+        (0..num_qubits).fold(DMatrix::from_element(1, 1, 1.0), |acc, _| acc.kronecker(&single))
+    }
+}
+
+/// Measurement-error mitigation by inverting a calibration (confusion) matrix
+#[derive(Clone, Debug)]
+pub struct ReadoutMitigator {
+    calibration: DMatrix<f64>,
+    num_qubits: usize,
+}
+
+impl ReadoutMitigator {
+    /// Create a mitigator from a 2ⁿ x 2ⁿ calibration matrix with entries P(measured | true)
+    pub fn new(calibration: DMatrix<f64>) -> Result<Self, QsimError> {
+        let dim = calibration.nrows();
+        if dim != calibration.ncols() || !dim.is_power_of_two() {
+            return Err(QsimError::InvalidParameter(format!(
+                "Calibration matrix must be 2^n x 2^n, got {}x{}",
+                calibration.nrows(),
+                calibration.ncols()
+            )));
+        }
+
+        Ok(ReadoutMitigator {
+            calibration,
+            num_qubits: dim.trailing_zeros() as usize,
+        })
+    }
+
+    /// Create a mitigator for independent, identical readout errors on `num_qubits` qubits
+    /// The calibration is the tensor product of single-qubit confusion matrices
+    pub fn from_single_qubit_rates(p01: f64, p10: f64, num_qubits: usize) -> Result<Self, QsimError> {
+        if !(0.0..=1.0).contains(&p01) || !(0.0..=1.0).contains(&p10) {
+            return Err(QsimError::InvalidParameter(format!(
+                "Readout error rates must lie in [0, 1], got p01 = {}, p10 = {}",
+                p01, p10
+            )));
+        }
+        if (p01 + p10 - 1.0).abs() < 1e-12 {
+            return Err(QsimError::InvalidParameter(
+                "Readout with p01 + p10 = 1 carries no information and cannot be inverted".to_string(),
+            ));
+        }
+        ReadoutMitigator::new(ReadoutError::new(p01, p10).confusion_matrix(num_qubits))
+    }
+
+    /// Number of qubits the calibration covers
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    /// Correct a measured probability vector by solving M p_true = p_measured
+    /// Negative quasi-probabilities are clipped to zero and the result renormalized
+    pub fn mitigate_probabilities(&self, measured: &[f64]) -> Result<Vec<f64>, QsimError> {
+        if measured.len() != self.calibration.nrows() {
+            return Err(QsimError::InvalidParameter(format!(
+                "Expected {} probabilities, got {}",
+                self.calibration.nrows(),
+                measured.len()
+            )));
+        }
+
+        let corrected = self
+            .calibration
+            .clone()
+            .lu()
+            .solve(&DVector::from_column_slice(measured))
+            .ok_or_else(|| QsimError::InvalidParameter("Calibration matrix is singular".to_string()))?;
+
+        let clipped: Vec<f64> = corrected.iter().map(|&p| p.max(0.0)).collect();
+        let total: f64 = clipped.iter().sum();
+        if total <= 0.0 {
+            return Err(QsimError::InvalidParameter(
+                "Corrected distribution has no positive weight".to_string(),
+            ));
+        }
+        Ok(clipped.into_iter().map(|p| p / total).collect())
+    }
+
+    /// Correct a histogram of measured bitstrings, returning the mitigated probability
+    /// of every bitstring with non-zero weight
+    pub fn apply_correction(
+        &self,
+        counts: &HashMap<String, usize>,
+    ) -> Result<HashMap<String, f64>, QsimError> {
+        let measured = counts_to_distribution(counts, self.num_qubits)?;
+        let corrected = self.mitigate_probabilities(&measured)?;
+
+        Ok(corrected
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, p)| p > 0.0)
+            .map(|(index, p)| (index_to_bitstring(index, self.num_qubits), p))
+            .collect())
+    }
+
+    /// Mitigated expectation of a Pauli string from counts measured in its eigenbasis
+    /// The counts are corrected first, then reduced to the parity over the string's
+    /// non-identity positions (character i acts on wire i)
+    pub fn correct_expectation(
+        &self,
+        raw_counts: &HashMap<String, usize>,
+        pauli_string: &str,
+    ) -> Result<f64, QsimError> {
+        if pauli_string.chars().count() != self.num_qubits {
+            return Err(QsimError::InvalidParameter(format!(
+                "Pauli string '{}' has length {}, expected {}",
+                pauli_string,
+                pauli_string.chars().count(),
+                self.num_qubits
+            )));
+        }
+        if let Some(op) = pauli_string.chars().find(|c| !"IXYZ".contains(*c)) {
+            return Err(QsimError::InvalidParameter(format!(
+                "Invalid Pauli operator '{}' in '{}'",
+                op,
+                pauli_string
+            )));
+        }
+
+        let support: Vec<usize> = pauli_string
+            .chars()
+            .enumerate()
+            .filter(|&(_, op)| op != 'I')
+            .map(|(w, _)| w)
+            .collect();
+        let corrected = self.apply_correction(raw_counts)?;
+        Ok(parity_expectation(&corrected, &support))
+    }
+}
+
+/// Expectation of the Z-parity ∏ Zᵢ over `qubits` for a (not necessarily normalized)
+/// distribution over bitstrings
+pub fn parity_expectation(distribution: &HashMap<String, f64>, qubits: &[usize]) -> f64 {
+    let mut total = 0.0;
+    let mut signed = 0.0;
+    for (bitstring, &weight) in distribution {
+        let bits = bitstring.as_bytes();
+        let ones = qubits.iter().filter(|&&q| bits.get(q) == Some(&b'1')).count();
+        signed += if ones % 2 == 1 { -weight } else { weight };
+        total += weight;
+    }
+
+    if total > 0.0 {
+        signed / total
+    } else {
+        0.0
+    }
+}
+
+/// Format a basis-state index as a bitstring with wire 0 first
+pub fn index_to_bitstring(index: usize, num_qubits: usize) -> String {
+    (0..num_qubits)
+        .map(|i| if (index >> (num_qubits - 1 - i)) & 1 == 1 { '1' } else { '0' })
+        .collect()
+}
+
+/// Normalized probability vector (indexed by basis state) from bitstring counts
+pub(crate) fn counts_to_distribution(
+    counts: &HashMap<String, usize>,
+    num_qubits: usize,
+) -> Result<Vec<f64>, QsimError> {
+    let mut probs = vec![0.0; 1 << num_qubits];
+    let mut total = 0usize;
+    for (bitstring, &count) in counts {
+        if bitstring.len() != num_qubits {
+            return Err(QsimError::InvalidParameter(format!(
+                "Bitstring '{}' has length {}, expected {}",
+                bitstring,
+                bitstring.len(),
+                num_qubits
+            )));
+        }
+        let index = usize::from_str_radix(bitstring, 2)
+            .map_err(|_| QsimError::InvalidParameter(format!("Invalid bitstring '{}'", bitstring)))?;
+        probs[index] += count as f64;
+        total += count;
+    }
+
+    if total == 0 {
+        return Err(QsimError::InvalidParameter("Counts are empty".to_string()));
+    }
+    Ok(probs.into_iter().map(|c| c / total as f64).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_confusion_matrix_columns_are_distributions() {
+        let m = ReadoutError::new(0.05, 0.1).confusion_matrix(2);
+        assert_eq!(m.nrows(), 4);
+        for col in m.column_iter() {
+            assert_relative_eq!(col.sum(), 1.0, epsilon = 1e-12);
+        }
+        // P(measure 11 | true 00) = p01²
+        assert_relative_eq!(m[(3, 0)], 0.05 * 0.05, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_correct_expectation_removes_readout_bias() {
+        use crate::simulator::QuantumSimulator;
+
+        let mut sim = QuantumSimulator::new(2);
+        sim.set_seed(23);
+        sim.apply_gate("RY", &[0], &[0.5]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+
+        let err = ReadoutError::new(0.08, 0.15);
+        let mitigator = ReadoutMitigator::new(err.confusion_matrix(2)).unwrap();
+        let raw = sim.measure_counts_with_readout_error(100_000, &err);
+
+        let raw_distribution: HashMap<String, f64> =
+            raw.iter().map(|(k, &c)| (k.clone(), c as f64)).collect();
+        for (pauli, support) in [("ZI", vec![0]), ("ZZ", vec![0, 1])] {
+            let exact = sim.expectation_pauli(pauli).unwrap();
+            let biased = parity_expectation(&raw_distribution, &support);
+            let corrected = mitigator.correct_expectation(&raw, pauli).unwrap();
+
+            assert!((biased - exact).abs() > 0.1);
+            assert!((corrected - exact).abs() < 0.02, "{}: {} vs {}", pauli, corrected, exact);
+        }
+        assert!(mitigator.correct_expectation(&raw, "Z").is_err());
+    }
+
+    #[test]
+    fn test_from_single_qubit_rates_recovers_distribution() {
+        let mitigator = ReadoutMitigator::from_single_qubit_rates(0.05, 0.05, 2).unwrap();
+        assert_eq!(mitigator.num_qubits(), 2);
+
+        // Corrupt a known distribution by the exact 5% flip channel and round to counts
+        let truth = [0.6, 0.0, 0.1, 0.3];
+        let confusion = ReadoutError::new(0.05, 0.05).confusion_matrix(2);
+        let measured = confusion * DVector::from_column_slice(&truth);
+        let shots = 1_000_000.0;
+        let counts: HashMap<String, usize> = measured
+            .iter()
+            .enumerate()
+            .map(|(index, &p)| (index_to_bitstring(index, 2), (p * shots).round() as usize))
+            .collect();
+        assert!(counts["01"] > 0);
+
+        let corrected = mitigator.apply_correction(&counts).unwrap();
+        for (index, &p) in truth.iter().enumerate() {
+            let recovered = corrected.get(&index_to_bitstring(index, 2)).copied().unwrap_or(0.0);
+            assert_relative_eq!(recovered, p, epsilon = 1e-5);
+        }
+        assert_relative_eq!(corrected.values().sum::<f64>(), 1.0, epsilon = 1e-12);
+
+        assert!(ReadoutMitigator::from_single_qubit_rates(1.2, 0.0, 1).is_err());
+        assert!(ReadoutMitigator::from_single_qubit_rates(0.5, 0.5, 1).is_err());
+    }
+}
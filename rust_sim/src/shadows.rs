@@ -0,0 +1,91 @@
+use crate::error::QsimError;
+
+/// One randomized measurement: the Pauli basis used on each qubit and the observed bits
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub bases: Vec<char>,
+    pub outcome: Vec<usize>,
+}
+
+/// Classical shadow of a state built from random single-qubit Pauli-basis measurements
+///
+/// Each snapshot inverts the measurement channel qubit by qubit, so a Pauli string P is
+/// estimated by averaging Πᵢ 3·(±1) over snapshots whose bases match P on its support
+/// (and 0 otherwise). The number of snapshots needed grows as 3^weight(P), independent
+/// of how many observables are estimated from the same shadow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassicalShadow {
+    pub snapshots: Vec<Snapshot>,
+    pub num_qubits: usize,
+}
+
+impl ClassicalShadow {
+    /// Estimate ⟨P⟩ for a Pauli string such as "XIZ" (character i acts on wire i)
+    pub fn estimate(&self, pauli_string: &str) -> Result<f64, QsimError> {
+        let pauli: Vec<char> = pauli_string.chars().collect();
+        if pauli.len() != self.num_qubits {
+            return Err(QsimError::InvalidParameter(format!(
+                "Pauli string '{}' has length {}, expected {}",
+                pauli_string,
+                pauli.len(),
+                self.num_qubits
+            )));
+        }
+        if let Some(op) = pauli.iter().find(|op| !"IXYZ".contains(**op)) {
+            return Err(QsimError::InvalidParameter(format!(
+                "Invalid Pauli operator '{}' in '{}'",
+                op,
+                pauli_string
+            )));
+        }
+        if self.snapshots.is_empty() {
+            return Err(QsimError::InvalidParameter("Shadow has no snapshots".to_string()));
+        }
+
+        let total: f64 = self
+            .snapshots
+            .iter()
+            .map(|snap| {
+                pauli
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &op)| op != 'I')
+                    .map(|(w, &op)| {
+                        if snap.bases[w] != op {
+                            0.0
+                        } else if snap.outcome[w] == 1 {
+                            -3.0
+                        } else {
+                            3.0
+                        }
+                    })
+                    .product::<f64>()
+            })
+            .sum();
+
+        Ok(total / self.snapshots.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulator::QuantumSimulator;
+
+    #[test]
+    fn test_shadow_estimates_converge() {
+        let mut sim = QuantumSimulator::new(2);
+        sim.set_seed(17);
+        sim.apply_gate("RY", &[0], &[1.1]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        sim.apply_gate("RX", &[1], &[0.4]).unwrap();
+
+        let shadow = sim.classical_shadow(20_000);
+        assert_eq!(shadow.snapshots.len(), 20_000);
+        for pauli in ["ZI", "IZ", "ZZ", "XX", "YY", "XZ"] {
+            let exact = sim.expectation_pauli(pauli).unwrap();
+            let estimate = shadow.estimate(pauli).unwrap();
+            assert!((estimate - exact).abs() < 0.1, "{}: {} vs {}", pauli, estimate, exact);
+        }
+        assert!(shadow.estimate("ZQ").is_err());
+    }
+}
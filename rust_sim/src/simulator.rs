@@ -1,16 +1,100 @@
-use nalgebra::DMatrix;
+use nalgebra::{DMatrix, DVector};
 use num_complex::Complex;
-use crate::density_matrix::DensityMatrix;
+use crate::circuit::{Circuit, GateOp};
+use crate::density_matrix::{DensityMatrix, UnitaryWorkspace};
+use crate::error::QsimError;
 use crate::gates::*;
 use crate::noise_model::*;
-use rand::Rng;
+use crate::readout::{index_to_bitstring, parity_expectation, ReadoutError, ReadoutMitigator};
+use crate::shadows::{ClassicalShadow, Snapshot};
 use rand::distributions::WeightedIndex;
 use rand::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Operator produced by resolving a named gate
+enum GateAction {
+    /// 2x2 matrix on a single wire, applied in place without forming the full operator
+    SingleQubit(DMatrix<Complex<f64>>, usize),
+    /// Full-system unitary
+    Full(DMatrix<Complex<f64>>),
+    /// Diagonal unitary given by its 2ⁿ phases, applied elementwise
+    Diagonal(Vec<Complex<f64>>),
+}
+
+impl GateAction {
+    fn adjoint(self) -> Self {
+        match self {
+            GateAction::SingleQubit(gate, wire) => GateAction::SingleQubit(gate.adjoint(), wire),
+            GateAction::Full(unitary) => GateAction::Full(unitary.adjoint()),
+            GateAction::Diagonal(phases) => GateAction::Diagonal(phases.iter().map(|p| p.conj()).collect()),
+        }
+    }
+
+    /// The operator as a full-register unitary
+    fn into_full(self, num_qubits: usize) -> DMatrix<Complex<f64>> {
+        match self {
+            GateAction::SingleQubit(gate, wire) => build_single_qubit_unitary(&gate, wire, num_qubits),
+            GateAction::Full(unitary) => unitary,
+            GateAction::Diagonal(phases) => DMatrix::from_diagonal(&DVector::from_vec(phases)),
+        }
+    }
+
+    fn apply(&self, rho: &mut DensityMatrix, workspace: &mut UnitaryWorkspace) {
+        match self {
+            GateAction::SingleQubit(gate, wire) => apply_single_qubit_gate_inplace(rho, gate, *wire),
+            GateAction::Full(unitary) => rho.apply_unitary_in_place(unitary, workspace),
+            GateAction::Diagonal(phases) => rho.apply_diagonal(phases),
+        }
+    }
+}
+
+/// Lazily sampled measurement shots, returned by `QuantumSimulator::shot_iterator`
+/// Owns its own RNG (seeded from the simulator's), so it does not borrow the simulator
+pub struct ShotIterator {
+    dist: WeightedIndex<f64>,
+    rng: StdRng,
+    num_qubits: usize,
+    remaining: usize,
+}
+
+impl Iterator for ShotIterator {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let index = self.dist.sample(&mut self.rng);
+        Some((0..self.num_qubits).map(|i| (index >> (self.num_qubits - 1 - i)) & 1).collect())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for ShotIterator {}
+
+/// Mixed into the seed of `set_seed` so the noise stream differs from the measurement stream
+const NOISE_STREAM: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Gate instruction with optional attached noise: (name, wires, params, channel)
+pub type NoisyOp = (String, Vec<usize>, Vec<f64>, Option<Channel>);
+
+/// Largest |Tr(ρ) - 1| that `run_with_trace_monitor` treats as numerical noise
+const TRACE_TOLERANCE: f64 = 1e-10;
 
 /// Main quantum simulator using density matrix formalism
 pub struct QuantumSimulator {
     state: DensityMatrix,
     num_qubits: usize,
+    /// Drives measurement sampling and randomized measurement bases
+    measurement_rng: RefCell<StdRng>,
+    /// Drives stochastic noise such as injected Pauli errors
+    noise_rng: RefCell<StdRng>,
+    workspace: UnitaryWorkspace,
 }
 
 impl QuantumSimulator {
@@ -19,9 +103,29 @@ impl QuantumSimulator {
         QuantumSimulator {
             state: DensityMatrix::new(num_qubits),
             num_qubits,
+            measurement_rng: RefCell::new(StdRng::from_entropy()),
+            noise_rng: RefCell::new(StdRng::from_entropy()),
+            workspace: UnitaryWorkspace::default(),
         }
     }
 
+    /// Seed both the measurement and the noise random number generators, for reproducible
+    /// runs; the two streams are derived from `seed` but stay independent
+    pub fn set_seed(&mut self, seed: u64) {
+        self.set_measurement_seed(seed);
+        self.set_noise_seed(seed ^ NOISE_STREAM);
+    }
+
+    /// Seed only the generator used for measurement sampling
+    pub fn set_measurement_seed(&mut self, seed: u64) {
+        self.measurement_rng = RefCell::new(StdRng::seed_from_u64(seed));
+    }
+
+    /// Seed only the generator used for stochastic noise, fixing the noise realization
+    pub fn set_noise_seed(&mut self, seed: u64) {
+        self.noise_rng = RefCell::new(StdRng::seed_from_u64(seed));
+    }
+
     /// Reset to |0...0⟩ state
     pub fn reset(&mut self) {
         self.state = DensityMatrix::new(self.num_qubits);
@@ -32,69 +136,558 @@ impl QuantumSimulator {
         &self.state
     }
 
+    /// Mutable access to the state for crate-internal routines that prepare custom inputs
+    pub(crate) fn state_mut(&mut self) -> &mut DensityMatrix {
+        &mut self.state
+    }
+
     /// Apply a quantum gate
     pub fn apply_gate(
         &mut self,
         gate_name: &str,
         wires: &[usize],
         params: &[f64],
-    ) -> Result<(), String> {
-        let unitary = match gate_name {
-            "PauliX" | "X" => {
-                if wires.len() != 1 {
-                    return Err("PauliX requires exactly 1 wire".to_string());
+    ) -> Result<(), QsimError> {
+        self.resolve_gate(gate_name, wires, params)?.apply(&mut self.state, &mut self.workspace);
+        Ok(())
+    }
+
+    /// Apply one circuit instruction: a named gate, or the unitary of a custom-matrix op
+    pub fn apply_op(&mut self, op: &GateOp) -> Result<(), QsimError> {
+        self.resolve_op(op)?.apply(&mut self.state, &mut self.workspace);
+        Ok(())
+    }
+
+    /// Full-register unitary of a named gate, built exactly as `apply_gate` would but not applied
+    pub fn gate_unitary(
+        &self,
+        gate_name: &str,
+        wires: &[usize],
+        params: &[f64],
+    ) -> Result<DMatrix<Complex<f64>>, QsimError> {
+        Ok(self.resolve_gate(gate_name, wires, params)?.into_full(self.num_qubits))
+    }
+
+    /// Apply a named gate together with its gate error from `noise`, placed before, after
+    /// or on both sides of the gate according to `noise.placement`
+    pub fn apply_gate_noisy(
+        &mut self,
+        gate_name: &str,
+        wires: &[usize],
+        params: &[f64],
+        noise: &NoiseModel,
+    ) -> Result<(), QsimError> {
+        let action = self.resolve_gate(gate_name, wires, params)?;
+        self.apply_action_noisy(&action, wires, noise);
+        Ok(())
+    }
+
+    /// Apply a resolved gate with its gate error placed according to `noise.placement`
+    fn apply_action_noisy(&mut self, action: &GateAction, wires: &[usize], noise: &NoiseModel) {
+        if noise.placement.before() {
+            noise.apply_gate_noise(&mut self.state, wires);
+        }
+        action.apply(&mut self.state, &mut self.workspace);
+        if noise.placement.after() {
+            noise.apply_gate_noise(&mut self.state, wires);
+        }
+    }
+
+    /// Apply the inverse (adjoint) of a named quantum gate
+    /// Takes the same arguments as `apply_gate`, so echo sequences can undo a gate directly
+    pub fn apply_gate_inverse(
+        &mut self,
+        gate_name: &str,
+        wires: &[usize],
+        params: &[f64],
+    ) -> Result<(), QsimError> {
+        self.resolve_gate(gate_name, wires, params)?
+            .adjoint()
+            .apply(&mut self.state, &mut self.workspace);
+        Ok(())
+    }
+
+    /// Apply every gate of `subcircuit` controlled on `control`
+    /// Each gate U becomes |0⟩⟨0| ⊗ I + |1⟩⟨1| ⊗ U; the subcircuit must not touch `control`
+    pub fn apply_controlled_circuit(
+        &mut self,
+        control: usize,
+        subcircuit: &Circuit,
+    ) -> Result<(), QsimError> {
+        if control >= self.num_qubits {
+            return Err(QsimError::WireOutOfRange(control));
+        }
+        if let Some(op) = subcircuit.ops.iter().find(|op| op.wires.contains(&control)) {
+            return Err(QsimError::InvalidParameter(format!(
+                "Gate {} acts on the control wire {}",
+                op.name,
+                control
+            )));
+        }
+
+        let bit = 1 << (self.num_qubits - 1 - control);
+        for op in &subcircuit.ops {
+            let unitary = self.resolve_op(op)?.into_full(self.num_qubits);
+            // U acts as the identity on the control, so rows with the control set keep U
+            let controlled = DMatrix::from_fn(unitary.nrows(), unitary.ncols(), |i, j| {
+                if i & bit != 0 {
+                    unitary[(i, j)]
+                } else if i == j {
+                    Complex::new(1.0, 0.0)
+                } else {
+                    Complex::new(0.0, 0.0)
                 }
-                build_single_qubit_unitary(&pauli_x(), wires[0], self.num_qubits)
+            });
+            self.state.apply_unitary_in_place(&controlled, &mut self.workspace);
+        }
+        Ok(())
+    }
+
+    /// Apply an arbitrary unitary `base` on `target_wires` only when every control wire is |1⟩
+    /// `base` is 2ᵏ×2ᵏ for k target wires, with target_wires[0] as its most significant bit
+    pub fn apply_controlled_matrix(
+        &mut self,
+        base: &DMatrix<Complex<f64>>,
+        controls: &[usize],
+        target_wires: &[usize],
+    ) -> Result<(), QsimError> {
+        let controlled = self.controlled_matrix_unitary(base, controls, target_wires)?;
+        self.state.apply_unitary_in_place(&controlled, &mut self.workspace);
+        Ok(())
+    }
+
+    /// Full-register unitary applying `base` on `target_wires` when every control is |1⟩
+    fn controlled_matrix_unitary(
+        &self,
+        base: &DMatrix<Complex<f64>>,
+        controls: &[usize],
+        target_wires: &[usize],
+    ) -> Result<DMatrix<Complex<f64>>, QsimError> {
+        let local_dim = 1 << target_wires.len();
+        if base.shape() != (local_dim, local_dim) {
+            return Err(QsimError::InvalidParameter(format!(
+                "Matrix is {}x{}, expected {}x{} for {} target wire(s)",
+                base.nrows(),
+                base.ncols(),
+                local_dim,
+                local_dim,
+                target_wires.len()
+            )));
+        }
+        let all_wires: Vec<usize> = controls.iter().chain(target_wires).copied().collect();
+        for (i, &w) in all_wires.iter().enumerate() {
+            if w >= self.num_qubits {
+                return Err(QsimError::WireOutOfRange(w));
+            }
+            if all_wires[..i].contains(&w) {
+                return Err(QsimError::InvalidParameter(format!("Wire {} used more than once", w)));
+            }
+        }
+        let identity = DMatrix::<Complex<f64>>::identity(local_dim, local_dim);
+        if (base.adjoint() * base - identity).norm() > 1e-9 {
+            return Err(QsimError::NotUnitary);
+        }
+
+        let n = self.num_qubits;
+        let control_mask = controls.iter().fold(0, |mask, &w| mask | (1 << (n - 1 - w)));
+        let target_mask = target_wires.iter().fold(0, |mask, &w| mask | (1 << (n - 1 - w)));
+        // Gather the target bits of a full-register index into a local index of `base`
+        let local = |index: usize| {
+            target_wires
+                .iter()
+                .fold(0, |acc, &w| (acc << 1) | ((index >> (n - 1 - w)) & 1))
+        };
+        let dim = 1 << n;
+        let controlled = DMatrix::from_fn(dim, dim, |i, j| {
+            if i & !target_mask != j & !target_mask {
+                Complex::new(0.0, 0.0)
+            } else if j & control_mask == control_mask {
+                base[(local(i), local(j))]
+            } else if i == j {
+                Complex::new(1.0, 0.0)
+            } else {
+                Complex::new(0.0, 0.0)
+            }
+        });
+        Ok(controlled)
+    }
+
+    /// Apply a gate and a noise channel to every qubit *not* acted on by the gate
+    /// Isolates crosstalk from a gate onto its idle neighbours (spectator errors)
+    pub fn apply_gate_with_spectator_noise(
+        &mut self,
+        gate_name: &str,
+        wires: &[usize],
+        params: &[f64],
+        spectator_noise: ChannelKind,
+        strength: f64,
+    ) -> Result<(), QsimError> {
+        self.apply_gate(gate_name, wires, params)?;
+        for spectator in (0..self.num_qubits).filter(|w| !wires.contains(w)) {
+            apply_channel(&mut self.state, spectator, spectator_noise, strength);
+        }
+        Ok(())
+    }
+
+    /// Build the operator of a circuit instruction, taking a custom-matrix op's own unitary
+    fn resolve_op(&self, op: &GateOp) -> Result<GateAction, QsimError> {
+        if op.matrix.is_none() {
+            return self.resolve_gate(&op.name, &op.wires, &op.params);
+        }
+        let unitary = op.custom_matrix().ok_or_else(|| {
+            QsimError::InvalidParameter(format!(
+                "Matrix op on {} wire(s) has the wrong number of entries",
+                op.wires.len()
+            ))
+        })?;
+        Ok(GateAction::Full(self.controlled_matrix_unitary(&unitary, &[], &op.wires)?))
+    }
+
+    /// Look up a named gate and build the operator it applies
+    fn resolve_gate(
+        &self,
+        gate_name: &str,
+        wires: &[usize],
+        params: &[f64],
+    ) -> Result<GateAction, QsimError> {
+        if let Some(&w) = wires.iter().find(|&&w| w >= self.num_qubits) {
+            return Err(QsimError::WireOutOfRange(w));
+        }
+        let require = |n_wires: usize, n_params: usize| {
+            if wires.len() != n_wires {
+                return Err(QsimError::WrongWireCount {
+                    expected: n_wires,
+                    got: wires.len(),
+                });
+            }
+            if params.len() < n_params {
+                return Err(QsimError::InvalidParameter(format!(
+                    "{} requires {} parameter(s), got {}",
+                    gate_name,
+                    n_params,
+                    params.len()
+                )));
+            }
+            Ok(())
+        };
+
+        let action = match gate_name {
+            "PauliX" | "X" => {
+                require(1, 0)?;
+                GateAction::SingleQubit(pauli_x(), wires[0])
             },
             "PauliY" | "Y" => {
-                if wires.len() != 1 {
-                    return Err("PauliY requires exactly 1 wire".to_string());
-                }
-                build_single_qubit_unitary(&pauli_y(), wires[0], self.num_qubits)
+                require(1, 0)?;
+                GateAction::SingleQubit(pauli_y(), wires[0])
             },
             "PauliZ" | "Z" => {
-                if wires.len() != 1 {
-                    return Err("PauliZ requires exactly 1 wire".to_string());
-                }
-                build_single_qubit_unitary(&pauli_z(), wires[0], self.num_qubits)
+                require(1, 0)?;
+                let z = pauli_z().diagonal();
+                GateAction::Diagonal(expand_diagonal(z.as_slice(), wires, self.num_qubits))
             },
             "Hadamard" | "H" => {
-                if wires.len() != 1 {
-                    return Err("Hadamard requires exactly 1 wire".to_string());
-                }
-                build_single_qubit_unitary(&hadamard(), wires[0], self.num_qubits)
+                require(1, 0)?;
+                GateAction::SingleQubit(hadamard(), wires[0])
+            },
+            "S" | "Sdg" | "T" | "Tdg" => {
+                require(1, 0)?;
+                let gate = match gate_name {
+                    "S" => s_gate(),
+                    "Sdg" => s_dagger(),
+                    "T" => t_gate(),
+                    _ => t_dagger(),
+                };
+                let phases = gate.diagonal();
+                GateAction::Diagonal(expand_diagonal(phases.as_slice(), wires, self.num_qubits))
             },
             "RX" => {
-                if wires.len() != 1 || params.is_empty() {
-                    return Err("RX requires 1 wire and 1 parameter".to_string());
-                }
-                build_single_qubit_unitary(&rx(params[0]), wires[0], self.num_qubits)
+                require(1, 1)?;
+                GateAction::SingleQubit(rx(params[0]), wires[0])
+            },
+            "GlobalPhase" => {
+                require(1, 1)?;
+                let phase = Complex::new(0.0, params[0]).exp();
+                GateAction::Diagonal(vec![phase; 1 << self.num_qubits])
             },
             "RY" => {
-                if wires.len() != 1 || params.is_empty() {
-                    return Err("RY requires 1 wire and 1 parameter".to_string());
-                }
-                build_single_qubit_unitary(&ry(params[0]), wires[0], self.num_qubits)
+                require(1, 1)?;
+                GateAction::SingleQubit(ry(params[0]), wires[0])
             },
             "RZ" => {
-                if wires.len() != 1 || params.is_empty() {
-                    return Err("RZ requires 1 wire and 1 parameter".to_string());
+                require(1, 1)?;
+                let phases = rz(params[0]).diagonal();
+                GateAction::Diagonal(expand_diagonal(phases.as_slice(), wires, self.num_qubits))
+            },
+            "U3" => {
+                require(1, 3)?;
+                if params.len() != 3 {
+                    return Err(QsimError::InvalidParameter(format!(
+                        "U3 takes exactly 3 parameters (theta, phi, lambda), got {}",
+                        params.len()
+                    )));
                 }
-                build_single_qubit_unitary(&rz(params[0]), wires[0], self.num_qubits)
+                GateAction::SingleQubit(u3(params[0], params[1], params[2]), wires[0])
             },
             "CNOT" | "CX" => {
-                if wires.len() != 2 {
-                    return Err("CNOT requires exactly 2 wires".to_string());
+                require(2, 0)?;
+                GateAction::Full(build_cnot_unitary(wires[0], wires[1], self.num_qubits))
+            },
+            "Toffoli" | "CCX" => {
+                require(3, 0)?;
+                if wires[0] == wires[1] || wires[0] == wires[2] || wires[1] == wires[2] {
+                    return Err(QsimError::InvalidParameter(format!(
+                        "Toffoli wires must be distinct, got {:?}",
+                        wires
+                    )));
+                }
+                GateAction::Full(build_toffoli_unitary(wires[0], wires[1], wires[2], self.num_qubits))
+            },
+            "CZ" | "CY" | "CH" | "CRX" | "CRY" | "CRZ" | "CPhase" | "CP" => {
+                require(2, usize::from(!matches!(gate_name, "CZ" | "CY" | "CH")))?;
+                if wires[0] == wires[1] {
+                    return Err(QsimError::InvalidParameter(format!(
+                        "{} control and target must differ",
+                        gate_name
+                    )));
+                }
+                let gate = match gate_name {
+                    "CZ" => pauli_z(),
+                    "CY" => pauli_y(),
+                    "CH" => hadamard(),
+                    "CRX" => rx(params[0]),
+                    "CRY" => ry(params[0]),
+                    "CRZ" => rz(params[0]),
+                    _ => DMatrix::from_diagonal(&DVector::from_vec(vec![
+                        Complex::new(1.0, 0.0),
+                        Complex::new(0.0, params[0]).exp(),
+                    ])),
+                };
+                if matches!(gate_name, "CZ" | "CRZ" | "CPhase" | "CP") {
+                    // Diagonal target gate: the controlled gate is diag(1, 1, g₀, g₁)
+                    let one = Complex::new(1.0, 0.0);
+                    let phases = [one, one, gate[(0, 0)], gate[(1, 1)]];
+                    GateAction::Diagonal(expand_diagonal(&phases, wires, self.num_qubits))
+                } else {
+                    GateAction::Full(build_controlled_unitary(wires[0], wires[1], &gate, self.num_qubits))
+                }
+            },
+            "SWAP" => {
+                require(2, 0)?;
+                GateAction::Full(build_swap_unitary(wires[0], wires[1], self.num_qubits))
+            },
+            "RXX" | "RYY" | "RZZ" => {
+                require(2, 1)?;
+                if wires[0] == wires[1] {
+                    return Err(QsimError::InvalidParameter(format!(
+                        "{} needs two distinct wires",
+                        gate_name
+                    )));
+                }
+                let gate = match gate_name {
+                    "RXX" => rxx(params[0]),
+                    "RYY" => ryy(params[0]),
+                    _ => {
+                        let phases = rzz(params[0]).diagonal();
+                        return Ok(GateAction::Diagonal(expand_diagonal(
+                            phases.as_slice(),
+                            wires,
+                            self.num_qubits,
+                        )));
+                    }
+                };
+                GateAction::Full(build_two_qubit_unitary(&gate, wires[0], wires[1], self.num_qubits))
+            },
+            "XYZ" | "Canonical" => {
+                require(2, 3)?;
+                if wires[0] == wires[1] {
+                    return Err(QsimError::InvalidParameter(format!(
+                        "{} needs two distinct wires",
+                        gate_name
+                    )));
+                }
+                let gate = xyz_interaction(params[0], params[1], params[2]);
+                GateAction::Full(build_two_qubit_unitary(&gate, wires[0], wires[1], self.num_qubits))
+            },
+            "CPhaseFraction" => {
+                // Controlled phase 2π/2^k, the rotation used by the k-th QFT term
+                // A negative k gives the conjugate phase -2π/2^|k|, i.e. the inverse gate
+                require(2, 1)?;
+                if wires[0] == wires[1] {
+                    return Err(QsimError::InvalidParameter(
+                        "CPhaseFraction control and target must differ".to_string(),
+                    ));
+                }
+                let k = params[0];
+                if k == 0.0 || k.fract() != 0.0 {
+                    return Err(QsimError::InvalidParameter(format!(
+                        "CPhaseFraction requires a non-zero integer k, got {}",
+                        k
+                    )));
                 }
-                build_cnot_unitary(wires[0], wires[1], self.num_qubits)
+                let lam = k.signum() * 2.0 * std::f64::consts::PI / 2f64.powf(k.abs());
+                let one = Complex::new(1.0, 0.0);
+                let phases = [one, one, one, Complex::new(0.0, lam).exp()];
+                GateAction::Diagonal(expand_diagonal(&phases, wires, self.num_qubits))
             },
-            _ => return Err(format!("Unknown gate: {}", gate_name)),
+            _ => return Err(QsimError::UnknownGate(gate_name.to_string())),
         };
 
-        self.state.apply_unitary(&unitary);
+        Ok(action)
+    }
+
+    /// Run every gate of a circuit without noise
+    pub fn run_circuit(&mut self, circuit: &Circuit) -> Result<(), QsimError> {
+        for op in &circuit.ops {
+            self.apply_op(op)?;
+        }
+        Ok(())
+    }
+
+    /// Run a circuit layer by layer under a gate-level noise model
+    /// Each gate is followed by its gate error; wires not used in a layer receive idle noise
+    pub fn run_circuit_noisy(&mut self, circuit: &Circuit, noise: &NoiseModel) -> Result<(), QsimError> {
+        self.run_circuit_with_schedule(circuit, |_| noise.clone())
+    }
+
+    /// Run a circuit under noise that changes from layer to layer (e.g. calibration drift)
+    /// `schedule(layer_index)` gives the noise model used for that layer of `circuit.layers()`
+    pub fn run_circuit_with_schedule<F>(&mut self, circuit: &Circuit, schedule: F) -> Result<(), QsimError>
+    where
+        F: Fn(usize) -> NoiseModel,
+    {
+        for (layer_index, layer) in circuit.layers().into_iter().enumerate() {
+            self.run_layer(circuit, &layer, &schedule(layer_index))?;
+        }
+        Ok(())
+    }
+
+    /// Run a circuit under noise like `run_circuit_noisy`, recording Tr(ρ) after every layer
+    /// Returns the traces and whether any of them drifted from 1 by more than 1e-10
+    pub fn run_with_trace_monitor(
+        &mut self,
+        circuit: &Circuit,
+        noise: &NoiseModel,
+    ) -> Result<(Vec<f64>, bool), QsimError> {
+        let mut traces = Vec::new();
+        for layer in circuit.layers() {
+            self.run_layer(circuit, &layer, noise)?;
+            traces.push(self.state.trace().re);
+        }
+        let drifted = traces.iter().any(|t| (t - 1.0).abs() > TRACE_TOLERANCE);
+        Ok((traces, drifted))
+    }
+
+    /// Apply one layer of gates (indices into `circuit.ops`) with gate noise, then idle
+    /// noise on every wire the layer leaves untouched
+    fn run_layer(&mut self, circuit: &Circuit, layer: &[usize], noise: &NoiseModel) -> Result<(), QsimError> {
+        let mut busy = vec![false; self.num_qubits];
+        for &idx in layer {
+            let op = &circuit.ops[idx];
+            let action = self.resolve_op(op)?;
+            self.apply_action_noisy(&action, &op.wires, noise);
+            for &w in &op.wires {
+                busy[w] = true;
+            }
+        }
+        for wire in (0..self.num_qubits).filter(|&w| !busy[w]) {
+            noise.apply_idle_noise(&mut self.state, wire);
+        }
+        Ok(())
+    }
+
+    /// Run a gate list in which every instruction carries its own optional noise channel
+    /// A single-qubit channel attached to a gate is applied to each of its wires after the gate;
+    /// `None` leaves the gate ideal. Channels are checked before any gate runs, so a bad
+    /// channel leaves the state untouched
+    pub fn run_with_gate_noise(&mut self, ops: &[NoisyOp]) -> Result<(), QsimError> {
+        for (name, _, _, channel) in ops {
+            if let Some(channel) = channel {
+                if channel.kraus.iter().any(|k| k.shape() != (2, 2)) {
+                    return Err(QsimError::InvalidParameter(format!(
+                        "Noise attached to {} must be a single-qubit channel",
+                        name
+                    )));
+                }
+            }
+        }
+        for (name, wires, params, channel) in ops {
+            self.apply_gate(name, wires, params)?;
+            if let Some(channel) = channel {
+                for &wire in wires {
+                    channel.apply(&mut self.state, wire);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Prepare the joint +1 eigenstate of commuting, independent Pauli stabilizers
+    /// (e.g. ["XX", "ZZ"] for a Bell state) by projecting the maximally mixed state with
+    /// (I + S)/2 for each stabilizer S and renormalizing. Fewer than n stabilizers leave
+    /// the state maximally mixed over the stabilized subspace.
+    pub fn prepare_stabilizer_state(&mut self, stabilizers: &[String]) -> Result<(), QsimError> {
+        let mut symplectic = Vec::with_capacity(stabilizers.len());
+        let mut projectors = Vec::with_capacity(stabilizers.len());
+        for stabilizer in stabilizers {
+            if stabilizer.chars().count() != self.num_qubits {
+                return Err(QsimError::InvalidParameter(format!(
+                    "Stabilizer '{}' has length {}, expected {}",
+                    stabilizer,
+                    stabilizer.chars().count(),
+                    self.num_qubits
+                )));
+            }
+            let pauli = pauli_string_matrix(stabilizer)?;
+            let dim = pauli.nrows();
+            projectors.push((DMatrix::identity(dim, dim) + pauli) * Complex::new(0.5, 0.0));
+            symplectic.push(symplectic_form(stabilizer));
+        }
+
+        for (i, a) in stabilizers.iter().enumerate() {
+            for b in &stabilizers[i + 1..] {
+                let anticommuting = a
+                    .chars()
+                    .zip(b.chars())
+                    .filter(|&(p, q)| p != 'I' && q != 'I' && p != q)
+                    .count();
+                if anticommuting % 2 == 1 {
+                    return Err(QsimError::InvalidParameter(format!(
+                        "Stabilizers '{}' and '{}' do not commute",
+                        a,
+                        b
+                    )));
+                }
+            }
+        }
+        if gf2_rank(symplectic) < stabilizers.len() {
+            return Err(QsimError::InvalidParameter("Stabilizers are not independent".to_string()));
+        }
+
+        let dim = 1 << self.num_qubits;
+        let mut rho = DMatrix::identity(dim, dim);
+        for projector in &projectors {
+            rho = projector * rho * projector;
+        }
+        let trace = rho.trace();
+        self.state.matrix = rho / trace;
         Ok(())
     }
 
+    /// Run `circuit` under `noise`, then return the exact expectation of the Pauli string
+    /// `observable` together with an estimate from `n_shots` samples in its eigenbasis
+    /// Returns: (exact, sampled)
+    pub fn run_and_measure(
+        &mut self,
+        circuit: &Circuit,
+        noise: &NoiseModel,
+        observable: &str,
+        n_shots: usize,
+    ) -> Result<(f64, f64), QsimError> {
+        self.run_circuit_noisy(circuit, noise)?;
+        let exact = self.expectation_pauli(observable)?;
+        Ok((exact, self.sampled_pauli_expectation(observable, n_shots)?))
+    }
+
     /// Apply idle noise to a specific qubit
     pub fn apply_noise(&mut self, wire: usize, protected: bool) {
         if wire >= self.num_qubits {
@@ -111,6 +704,50 @@ impl QuantumSimulator {
         apply_amplitude_damping(&mut self.state, wire, gamma);
     }
 
+    /// Let `wire` sit idle for `total_time`, applying thermal relaxation (T1, T2) in
+    /// `n_steps` equal increments. The relaxation channels form a semigroup, so the result
+    /// does not depend on the discretization.
+    pub fn free_evolve(
+        &mut self,
+        wire: usize,
+        total_time: f64,
+        t1: f64,
+        t2: f64,
+        n_steps: usize,
+    ) -> Result<(), QsimError> {
+        if wire >= self.num_qubits {
+            return Err(QsimError::WireOutOfRange(wire));
+        }
+        if n_steps == 0 || total_time < 0.0 || t1 <= 0.0 || t2 <= 0.0 {
+            return Err(QsimError::InvalidParameter(format!(
+                "free_evolve needs n_steps >= 1, total_time >= 0 and positive T1, T2; got \
+                 n_steps = {}, total_time = {}, T1 = {}, T2 = {}",
+                n_steps, total_time, t1, t2
+            )));
+        }
+        let step = total_time / n_steps as f64;
+        for _ in 0..n_steps {
+            apply_thermal_relaxation(&mut self.state, wire, t1, t2, step);
+        }
+        Ok(())
+    }
+
+    /// Apply amplitude damping with a separate gamma per qubit (gammas[w] on wire w)
+    /// Zero entries are skipped
+    pub fn apply_amplitude_damping_layer(&mut self, gammas: &[f64]) -> Result<(), QsimError> {
+        if gammas.len() != self.num_qubits {
+            return Err(QsimError::InvalidParameter(format!(
+                "Expected {} gamma values, got {}",
+                self.num_qubits,
+                gammas.len()
+            )));
+        }
+        for (wire, &gamma) in gammas.iter().enumerate().filter(|&(_, &g)| g != 0.0) {
+            apply_amplitude_damping(&mut self.state, wire, gamma);
+        }
+        Ok(())
+    }
+
     /// Apply phase damping (T2) noise
     pub fn apply_phase_damping(&mut self, wire: usize, lambda: f64) {
         if wire >= self.num_qubits {
@@ -127,73 +764,637 @@ impl QuantumSimulator {
         apply_depolarizing(&mut self.state, wire, p);
     }
 
+    /// Coherence decay curve of a single qubit under repeated application of a channel
+    ///
+    /// Starts from a fresh |+⟩ on `wire` (all other qubits in |0⟩), applies the channel
+    /// `steps` times and records ⟨X⟩ on `wire` before the first and after every step.
+    /// Returns `steps + 1` values, starting at 1.0. The simulator state is left untouched.
+    pub fn coherence_decay(
+        &self,
+        wire: usize,
+        channel: ChannelKind,
+        param: f64,
+        steps: usize,
+    ) -> Vec<f64> {
+        if wire >= self.num_qubits {
+            return Vec::new();
+        }
+
+        let mut rho = DensityMatrix::new(self.num_qubits);
+        rho.apply_unitary(&build_single_qubit_unitary(&hadamard(), wire, self.num_qubits));
+        let x_obs = build_single_qubit_unitary(&pauli_x(), wire, self.num_qubits);
+        let x_expectation = |rho: &DensityMatrix| (&x_obs * &rho.matrix).trace().re;
+
+        let mut curve = Vec::with_capacity(steps + 1);
+        curve.push(x_expectation(&rho));
+        for _ in 0..steps {
+            apply_channel(&mut rho, wire, channel, param);
+            curve.push(x_expectation(&rho));
+        }
+        curve
+    }
+
+    /// Fault injection: each qubit independently suffers a uniformly random X, Y or Z
+    /// with probability `error_rate`, drawn from the simulator's noise RNG
+    /// Returns the injected errors as (wire, Pauli) pairs
+    pub fn inject_random_errors(&mut self, error_rate: f64) -> Vec<(usize, char)> {
+        let mut injected = Vec::new();
+        for wire in 0..self.num_qubits {
+            let mut rng = self.noise_rng.borrow_mut();
+            if rng.gen::<f64>() >= error_rate {
+                continue;
+            }
+            let (pauli, gate) = match rng.gen_range(0..3) {
+                0 => ('X', pauli_x()),
+                1 => ('Y', pauli_y()),
+                _ => ('Z', pauli_z()),
+            };
+            apply_single_qubit_gate_inplace(&mut self.state, &gate, wire);
+            injected.push((wire, pauli));
+        }
+        injected
+    }
+
+    /// Apply collective dephasing (a correlated Z error on all `wires`)
+    pub fn apply_collective_dephasing(&mut self, wires: &[usize], lambda: f64) {
+        if wires.iter().any(|&w| w >= self.num_qubits) {
+            return;
+        }
+        apply_collective_dephasing(&mut self.state, wires, lambda);
+    }
+
+    /// Encode the logical qubit on `logical_bit` into the decoherence-free subspace
+    /// spanned by |01⟩ and |10⟩ of wires (logical_bit, logical_bit + 1)
+    /// The partner wire must start in |0⟩: α|0⟩|0⟩ + β|1⟩|0⟩ → α|01⟩ + β|10⟩
+    pub fn encode_dfs(&mut self, logical_bit: usize) -> Result<(), QsimError> {
+        let partner = self.dfs_partner(logical_bit)?;
+        self.apply_gate("PauliX", &[partner], &[])?;
+        self.apply_gate("CNOT", &[logical_bit, partner], &[])
+    }
+
+    /// Decode a DFS-encoded logical qubit back onto `logical_bit`, returning the partner to |0⟩
+    pub fn decode_dfs(&mut self, logical_bit: usize) -> Result<(), QsimError> {
+        let partner = self.dfs_partner(logical_bit)?;
+        self.apply_gate("CNOT", &[logical_bit, partner], &[])?;
+        self.apply_gate("PauliX", &[partner], &[])
+    }
+
+    fn dfs_partner(&self, logical_bit: usize) -> Result<usize, QsimError> {
+        if logical_bit + 1 >= self.num_qubits {
+            return Err(QsimError::InvalidParameter(format!(
+                "DFS encoding of wire {} needs wire {} as partner, but only {} qubits exist",
+                logical_bit,
+                logical_bit + 1,
+                self.num_qubits
+            )));
+        }
+        Ok(logical_bit + 1)
+    }
+
     /// Measure all qubits and return single bitstring
     pub fn measure(&self) -> Vec<usize> {
         let probs = self.state.probabilities();
-        let mut rng = thread_rng();
         
         // Sample from probability distribution
         let dist = WeightedIndex::new(&probs).unwrap();
-        let outcome = dist.sample(&mut rng);
+        let outcome = dist.sample(&mut *self.measurement_rng.borrow_mut());
         
-        // Convert integer to bitstring
-        (0..self.num_qubits)
-            .map(|i| (outcome >> (self.num_qubits - 1 - i)) & 1)
-            .collect()
+        self.index_to_bits(outcome)
     }
 
-    /// Measure N shots and return all bitstrings
-    pub fn measure_shots(&self, n_shots: usize) -> Vec<Vec<usize>> {
-        (0..n_shots).map(|_| self.measure()).collect()
+    /// Return the outcome without sampling when the state is effectively classical
+    /// Gives the bitstring whose probability exceeds `threshold` (e.g. 1 - 1e-9),
+    /// or `None` if the state is genuinely superposed or mixed
+    pub fn measure_deterministic(&self, threshold: f64) -> Option<Vec<usize>> {
+        self.state
+            .probabilities()
+            .iter()
+            .position(|&p| p > threshold)
+            .map(|outcome| self.index_to_bits(outcome))
     }
 
-    /// Calculate expectation value of an observable (Pauli string)
-    pub fn expectation_value(&self, observable: &DMatrix<Complex<f64>>) -> f64 {
-        let result = observable * &self.state.matrix;
-        result.trace().re
+    /// Most probable computational-basis outcome (wire 0 first) and its probability,
+    /// read off the diagonal of ρ without sampling; ties go to the lowest index
+    pub fn most_likely_outcome(&self) -> (Vec<usize>, f64) {
+        let (index, p) = self
+            .state
+            .probabilities()
+            .into_iter()
+            .enumerate()
+            .fold((0, f64::NEG_INFINITY), |best, (i, p)| if p > best.1 { (i, p) } else { best });
+        (self.index_to_bits(index), p)
     }
 
-    /// Get trace and purity metrics
-    pub fn get_metrics(&self) -> (f64, f64) {
-        (self.state.trace().re, self.state.purity())
+    /// Convert a basis-state index to its bitstring (wire 0 first)
+    fn index_to_bits(&self, index: usize) -> Vec<usize> {
+        (0..self.num_qubits)
+            .map(|i| (index >> (self.num_qubits - 1 - i)) & 1)
+            .collect()
     }
 
-    /// Get the full density matrix as a flattened vector of complex numbers
-    /// Returns: (real_parts, imag_parts) as two separate vectors
-    pub fn get_density_matrix(&self) -> (Vec<f64>, Vec<f64>) {
+    /// Projectively measure one qubit, collapsing the simulator onto the sampled outcome
+    /// Returns the measured bit together with a copy of the post-measurement state
+    pub fn measure_qubit_keep_state(&mut self, wire: usize) -> Result<(usize, DensityMatrix), QsimError> {
+        if wire >= self.num_qubits {
+            return Err(QsimError::WireOutOfRange(wire));
+        }
+
+        let bit = 1 << (self.num_qubits - 1 - wire);
         let dim = self.state.dim();
-        let mut real_parts = Vec::with_capacity(dim * dim);
-        let mut imag_parts = Vec::with_capacity(dim * dim);
-        
+        let p_one: f64 = (0..dim).filter(|i| i & bit != 0).map(|i| self.state.matrix[(i, i)].re).sum();
+        let outcome = if self.measurement_rng.borrow_mut().gen::<f64>() < p_one { 1 } else { 0 };
+        let p_outcome = if outcome == 1 { p_one } else { 1.0 - p_one };
+
+        let keep = |i: usize| (i & bit != 0) == (outcome == 1);
         for i in 0..dim {
             for j in 0..dim {
-                let elem = self.state.matrix[(i, j)];
-                real_parts.push(elem.re);
-                imag_parts.push(elem.im);
+                self.state.matrix[(i, j)] = if keep(i) && keep(j) {
+                    self.state.matrix[(i, j)] / p_outcome
+                } else {
+                    Complex::new(0.0, 0.0)
+                };
             }
         }
-        
-        (real_parts, imag_parts)
+
+        Ok((outcome, self.state.clone()))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use approx::assert_relative_eq;
+    /// Measure N shots and return all bitstrings
+    pub fn measure_shots(&self, n_shots: usize) -> Vec<Vec<usize>> {
+        (0..n_shots).map(|_| self.measure()).collect()
+    }
 
-    #[test]
-    fn test_simulator_creation() {
-        let sim = QuantumSimulator::new(2);
-        let (trace, purity) = sim.get_metrics();
-        assert_relative_eq!(trace, 1.0, epsilon = 1e-10);
-        assert_relative_eq!(purity, 1.0, epsilon = 1e-10);
+    /// Histogram of N shots drawn with a Walker alias table, in O(2ⁿ + N)
+    /// Uses its own RNG seeded with `seed`, so identical calls give identical counts
+    pub fn measure_counts_fast(&self, n_shots: usize, seed: u64) -> HashMap<String, usize> {
+        let (threshold, alias) = alias_table(&self.state.probabilities());
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut tally = vec![0usize; threshold.len()];
+        for _ in 0..n_shots {
+            let column = rng.gen_range(0..threshold.len());
+            let index = if rng.gen::<f64>() < threshold[column] {
+                column
+            } else {
+                alias[column]
+            };
+            tally[index] += 1;
+        }
+
+        tally
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, c)| c > 0)
+            .map(|(index, c)| (index_to_bitstring(index, self.num_qubits), c))
+            .collect()
     }
 
-    #[test]
-    fn test_gate_application() {
-        let mut sim = QuantumSimulator::new(1);
-        sim.apply_gate("PauliX", &[0], &[]).unwrap();
+    /// Stream N shots one at a time without collecting them, for very large shot counts
+    /// Reproducible after `set_seed`: the iterator's RNG is seeded from the simulator's
+    pub fn shot_iterator(&self, n_shots: usize) -> ShotIterator {
+        ShotIterator {
+            dist: WeightedIndex::new(self.state.probabilities()).unwrap(),
+            rng: StdRng::seed_from_u64(self.measurement_rng.borrow_mut().gen()),
+            num_qubits: self.num_qubits,
+            remaining: n_shots,
+        }
+    }
+
+    /// Measure N shots and return a histogram of bitstrings (wire 0 first)
+    pub fn measure_counts(&self, n_shots: usize) -> HashMap<String, usize> {
+        self.sample_counts(&self.state.probabilities(), n_shots)
+    }
+
+    /// Estimate Tr(ρ²) from randomized measurements, without squaring ρ
+    ///
+    /// For each of `n_random_bases` draws, every qubit is rotated by an independent Haar-random
+    /// single-qubit unitary and measured `n_shots` times. The purity is the average over draws
+    /// of the unbiased cross-correlation 2ⁿ Σ_{i≠j} (-2)^(-D(sᵢ, sⱼ)) / (N(N-1)), where D is
+    /// the Hamming distance between shots i and j.
+    pub fn estimated_purity(&self, n_random_bases: usize, n_shots: usize) -> Result<f64, QsimError> {
+        if n_random_bases == 0 || n_shots < 2 {
+            return Err(QsimError::InvalidParameter(
+                "Purity estimation needs at least one basis and two shots".to_string(),
+            ));
+        }
+
+        let two_pi = 2.0 * std::f64::consts::PI;
+        let dim = (1usize << self.num_qubits) as f64;
+        let pairs = (n_shots * (n_shots - 1)) as f64;
+        let mut total = 0.0;
+
+        for _ in 0..n_random_bases {
+            // Haar measure in ZYZ Euler angles; the last RZ commutes with a Z measurement
+            let angles: Vec<(f64, f64)> = {
+                let mut rng = self.measurement_rng.borrow_mut();
+                (0..self.num_qubits)
+                    .map(|_| (two_pi * rng.gen::<f64>(), (1.0 - 2.0 * rng.gen::<f64>()).acos()))
+                    .collect()
+            };
+            let mut rotated = self.state.clone();
+            for (wire, &(gamma, beta)) in angles.iter().enumerate() {
+                apply_single_qubit_gate_inplace(&mut rotated, &rz(gamma), wire);
+                apply_single_qubit_gate_inplace(&mut rotated, &ry(beta), wire);
+            }
+
+            let counts: Vec<(usize, f64)> = self
+                .sample_counts(&rotated.probabilities(), n_shots)
+                .into_iter()
+                .map(|(bits, c)| (usize::from_str_radix(&bits, 2).unwrap(), c as f64))
+                .collect();
+            let mut correlation = -(n_shots as f64); // remove each shot paired with itself
+            for &(s, c_s) in &counts {
+                for &(t, c_t) in &counts {
+                    correlation += (-2f64).powi(-((s ^ t).count_ones() as i32)) * c_s * c_t;
+                }
+            }
+            total += dim * correlation / pairs;
+        }
+
+        Ok(total / n_random_bases as f64)
+    }
+
+    /// Ideal histogram for N shots without sampling: prob × N for every outcome with
+    /// non-zero probability, for comparison against sampled `measure_counts`
+    pub fn exact_counts(&self, n_shots: usize) -> HashMap<String, f64> {
+        self.state
+            .probabilities()
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, p)| p > 0.0)
+            .map(|(index, p)| (index_to_bitstring(index, self.num_qubits), p * n_shots as f64))
+            .collect()
+    }
+
+    /// Measure N shots in a per-qubit Pauli basis such as "XZI" (character i for wire i)
+    /// X and Y qubits are rotated onto Z (H and RX(π/2) respectively) on a copy of the
+    /// state before sampling; I and Z qubits are measured in the computational basis
+    pub fn measure_counts_in_basis(
+        &self,
+        basis: &str,
+        n_shots: usize,
+    ) -> Result<HashMap<String, usize>, QsimError> {
+        if basis.chars().count() != self.num_qubits {
+            return Err(QsimError::InvalidParameter(format!(
+                "Basis '{}' has length {}, expected {}",
+                basis,
+                basis.chars().count(),
+                self.num_qubits
+            )));
+        }
+
+        let mut rotated = self.state.clone();
+        for (wire, op) in basis.chars().enumerate() {
+            match op {
+                'I' | 'Z' => {}
+                'X' => apply_single_qubit_gate_inplace(&mut rotated, &hadamard(), wire),
+                'Y' => apply_single_qubit_gate_inplace(
+                    &mut rotated,
+                    &rx(std::f64::consts::FRAC_PI_2),
+                    wire,
+                ),
+                _ => {
+                    return Err(QsimError::InvalidParameter(format!(
+                        "Invalid Pauli operator '{}' in '{}'",
+                        op, basis
+                    )))
+                }
+            }
+        }
+
+        Ok(self.sample_counts(&rotated.probabilities(), n_shots))
+    }
+
+    /// Estimate ⟨P⟩ for a Pauli string from N shots, as hardware would
+    /// Each shot is measured in P's eigenbasis (see `measure_counts_in_basis`) and
+    /// contributes the ±1 parity of the outcomes on the non-identity qubits
+    pub fn sampled_pauli_expectation(&self, pauli: &str, n_shots: usize) -> Result<f64, QsimError> {
+        let counts = self.measure_counts_in_basis(pauli, n_shots)?;
+        let support: Vec<usize> = pauli
+            .chars()
+            .enumerate()
+            .filter(|&(_, op)| op != 'I')
+            .map(|(w, _)| w)
+            .collect();
+        let distribution: HashMap<String, f64> =
+            counts.into_iter().map(|(bits, c)| (bits, c as f64)).collect();
+        Ok(parity_expectation(&distribution, &support))
+    }
+
+    /// Collect a classical shadow: each snapshot measures every qubit once in a uniformly
+    /// random X, Y or Z basis, drawn from the simulator's seeded RNG
+    pub fn classical_shadow(&self, n_snapshots: usize) -> ClassicalShadow {
+        let snapshots = (0..n_snapshots)
+            .map(|_| {
+                let bases: Vec<char> = {
+                    let mut rng = self.measurement_rng.borrow_mut();
+                    (0..self.num_qubits).map(|_| ['X', 'Y', 'Z'][rng.gen_range(0..3)]).collect()
+                };
+                let basis: String = bases.iter().collect();
+                let counts = self
+                    .measure_counts_in_basis(&basis, 1)
+                    .expect("shadow bases are valid Pauli strings");
+                let bitstring = counts.keys().next().expect("one shot gives one outcome");
+                let outcome = bitstring.chars().map(|c| if c == '1' { 1 } else { 0 }).collect();
+                Snapshot { bases, outcome }
+            })
+            .collect();
+
+        ClassicalShadow {
+            snapshots,
+            num_qubits: self.num_qubits,
+        }
+    }
+
+    /// Draw N samples from a basis-state distribution into a bitstring histogram
+    fn sample_counts(&self, probs: &[f64], n_shots: usize) -> HashMap<String, usize> {
+        let dist = WeightedIndex::new(probs).unwrap();
+        let mut rng = self.measurement_rng.borrow_mut();
+        let mut counts = HashMap::new();
+        for _ in 0..n_shots {
+            let outcome = dist.sample(&mut *rng);
+            *counts.entry(index_to_bitstring(outcome, self.num_qubits)).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Measure N shots with classical readout error applied to every bit
+    pub fn measure_counts_with_readout_error(
+        &self,
+        n_shots: usize,
+        err: &ReadoutError,
+    ) -> HashMap<String, usize> {
+        let dist = WeightedIndex::new(self.state.probabilities()).unwrap();
+        let mut rng = self.measurement_rng.borrow_mut();
+        let mut counts = HashMap::new();
+        for _ in 0..n_shots {
+            let mut bits = self.index_to_bits(dist.sample(&mut *rng));
+            err.corrupt(&mut bits, &mut *rng);
+            let key: String = bits.iter().map(|b| if *b == 1 { '1' } else { '0' }).collect();
+            *counts.entry(key).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Sample with readout error, mitigate the histogram with the calibration matrix
+    /// `cal`, and return the corrected Z-parity expectation ⟨∏ Zᵢ⟩ over `qubits`
+    pub fn mitigated_expectation_z(
+        &self,
+        qubits: &[usize],
+        n_shots: usize,
+        err: &ReadoutError,
+        cal: &DMatrix<f64>,
+    ) -> Result<f64, QsimError> {
+        let mitigator = ReadoutMitigator::new(cal.clone())?;
+        if mitigator.num_qubits() != self.num_qubits {
+            return Err(QsimError::InvalidParameter(format!(
+                "Calibration covers {} qubits, simulator has {}",
+                mitigator.num_qubits(),
+                self.num_qubits
+            )));
+        }
+
+        let counts = self.measure_counts_with_readout_error(n_shots, err);
+        let corrected = mitigator.apply_correction(&counts)?;
+        Ok(parity_expectation(&corrected, qubits))
+    }
+
+    /// Per-qubit ⟨Zᵢ⟩ for every wire, read off the diagonal of ρ in one pass
+    pub fn z_expectations(&self) -> Vec<f64> {
+        let masks: Vec<usize> = (0..self.num_qubits).map(|w| 1 << w).collect();
+        self.state.diagonal_expectations(&masks)
+    }
+
+    /// All ⟨Zᵢ⟩ and the matrix of ⟨Zᵢ Zⱼ⟩ (ones on the diagonal), from one pass over ρ's diagonal
+    pub fn z_correlators(&self) -> (Vec<f64>, DMatrix<f64>) {
+        let n = self.num_qubits;
+        let pairs: Vec<(usize, usize)> = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j))).collect();
+        let masks: Vec<usize> = (0..n)
+            .map(|w| 1 << w)
+            .chain(pairs.iter().map(|&(i, j)| (1 << i) | (1 << j)))
+            .collect();
+        let values = self.state.diagonal_expectations(&masks);
+
+        let mut correlations = DMatrix::identity(n, n);
+        for (&(i, j), &value) in pairs.iter().zip(&values[n..]) {
+            correlations[(i, j)] = value;
+            correlations[(j, i)] = value;
+        }
+        (values[..n].to_vec(), correlations)
+    }
+
+    /// Calculate expectation value of an observable (Pauli string)
+    pub fn expectation_value(&self, observable: &DMatrix<Complex<f64>>) -> f64 {
+        let result = observable * &self.state.matrix;
+        result.trace().re
+    }
+
+    /// Checked `expectation_value`: the observable must match the register dimension and be
+    /// Hermitian within 1e-9, and Tr(Oρ) must come out real within the same tolerance
+    pub fn expectation_value_checked(&self, observable: &DMatrix<Complex<f64>>) -> Result<f64, QsimError> {
+        let dim = self.state.matrix.nrows();
+        if observable.shape() != (dim, dim) {
+            return Err(QsimError::InvalidParameter(format!(
+                "Observable is {}x{}, expected {}x{}",
+                observable.nrows(),
+                observable.ncols(),
+                dim,
+                dim
+            )));
+        }
+        if (observable - observable.adjoint()).norm() > 1e-9 {
+            return Err(QsimError::NotHermitian);
+        }
+        let value = (observable * &self.state.matrix).trace();
+        if value.im.abs() > 1e-9 {
+            return Err(QsimError::Unphysical(format!(
+                "Tr(Oρ) has imaginary part {:.3e}",
+                value.im
+            )));
+        }
+        Ok(value.re)
+    }
+
+    /// Calculate expectation value of a Pauli string such as "ZZ" or "XIZ" (character i acts on wire i)
+    pub fn expectation_pauli(&self, pauli: &str) -> Result<f64, QsimError> {
+        self.state.expectation_pauli(pauli)
+    }
+
+    /// Expectation of an entanglement witness W = Σ cⱼ Pⱼ given as weighted Pauli strings
+    /// A negative value certifies that the state is entangled
+    pub fn evaluate_witness(&self, witness: &[(f64, String)]) -> Result<f64, QsimError> {
+        witness
+            .iter()
+            .map(|(coeff, pauli)| Ok(coeff * self.expectation_pauli(pauli)?))
+            .sum()
+    }
+
+    /// Quantum mutual information I(a:b) = S(ρ_a) + S(ρ_b) - S(ρ_ab) in bits
+    pub fn mutual_information(&self, a: usize, b: usize) -> Result<f64, QsimError> {
+        if a >= self.num_qubits || b >= self.num_qubits || a == b {
+            return Err(QsimError::InvalidParameter(format!(
+                "Mutual information needs two distinct wires below {}, got {} and {}",
+                self.num_qubits, a, b
+            )));
+        }
+
+        let s_a = self.state.partial_trace(&[a])?.von_neumann_entropy();
+        let s_b = self.state.partial_trace(&[b])?.von_neumann_entropy();
+        let s_ab = self.state.partial_trace(&[a, b])?.von_neumann_entropy();
+        Ok(s_a + s_b - s_ab)
+    }
+
+    /// Trace distance between the current state and the initial |0...0⟩ state
+    pub fn trace_distance_to_initial(&self) -> f64 {
+        self.state
+            .trace_distance(&DensityMatrix::new(self.num_qubits))
+            .expect("initial state has the same register size")
+    }
+
+    /// Basis state whose probability differs most between this simulator and `other`
+    /// Ties go to the lowest basis index
+    /// Returns: (basis_index, |p_self - p_other|)
+    pub fn diff_report(&self, other: &QuantumSimulator) -> Result<(usize, f64), QsimError> {
+        if self.num_qubits != other.num_qubits {
+            return Err(QsimError::InvalidParameter(format!(
+                "Simulators have different register sizes: {} and {} qubits",
+                self.num_qubits, other.num_qubits
+            )));
+        }
+        Ok(self
+            .state
+            .probabilities()
+            .iter()
+            .zip(other.state.probabilities())
+            .map(|(p, q)| (p - q).abs())
+            .enumerate()
+            .fold((0, 0.0), |best, (index, diff)| if diff > best.1 { (index, diff) } else { best }))
+    }
+
+    /// Check that the state is physical within `tol`: unit trace, Hermitian and positive
+    /// semidefinite. The error names the first property that fails and by how much.
+    pub fn assert_physical(&self, tol: f64) -> Result<(), QsimError> {
+        let trace = self.state.trace();
+        if (trace - Complex::new(1.0, 0.0)).norm() > tol {
+            return Err(QsimError::Unphysical(format!(
+                "Trace is {:.6e} + {:.6e}i, expected 1",
+                trace.re,
+                trace.im
+            )));
+        }
+
+        let asymmetry = (&self.state.matrix - self.state.matrix.adjoint()).norm();
+        if asymmetry > tol {
+            return Err(QsimError::Unphysical(format!(
+                "State is not Hermitian: ||ρ - ρ†|| = {:.3e}",
+                asymmetry
+            )));
+        }
+
+        let min_eigenvalue = self.state.eigenvalues().last().copied().unwrap_or(0.0);
+        if min_eigenvalue < -tol {
+            return Err(QsimError::Unphysical(format!(
+                "State is not positive semidefinite: smallest eigenvalue {:.3e}",
+                min_eigenvalue
+            )));
+        }
+        Ok(())
+    }
+
+    /// Get trace and purity metrics
+    pub fn get_metrics(&self) -> (f64, f64) {
+        (self.state.trace().re, self.state.purity())
+    }
+
+    /// Get the full density matrix as a flattened vector of complex numbers
+    /// Returns: (real_parts, imag_parts) as two separate vectors
+    pub fn get_density_matrix(&self) -> (Vec<f64>, Vec<f64>) {
+        let dim = self.state.dim();
+        let mut real_parts = Vec::with_capacity(dim * dim);
+        let mut imag_parts = Vec::with_capacity(dim * dim);
+        
+        for i in 0..dim {
+            for j in 0..dim {
+                let elem = self.state.matrix[(i, j)];
+                real_parts.push(elem.re);
+                imag_parts.push(elem.im);
+            }
+        }
+        
+        (real_parts, imag_parts)
+    }
+}
+
+/// Binary symplectic vector of a Pauli string: X parts in the low bits, Z parts above
+fn symplectic_form(pauli: &str) -> u128 {
+    let n = pauli.chars().count();
+    pauli.chars().enumerate().fold(0, |acc, (w, op)| {
+        let x = matches!(op, 'X' | 'Y') as u128;
+        let z = matches!(op, 'Z' | 'Y') as u128;
+        acc | (x << w) | (z << (n + w))
+    })
+}
+
+/// Rank over GF(2) of a set of bit vectors, by Gaussian elimination
+fn gf2_rank(mut rows: Vec<u128>) -> usize {
+    let mut rank = 0;
+    while let Some(pivot_row) = rows.pop() {
+        if pivot_row == 0 {
+            continue;
+        }
+        rank += 1;
+        let pivot = pivot_row & pivot_row.wrapping_neg(); // lowest set bit
+        for row in rows.iter_mut() {
+            if *row & pivot != 0 {
+                *row ^= pivot_row;
+            }
+        }
+    }
+    rank
+}
+
+/// Vose's alias table for sampling index i with probability ∝ probs[i]
+/// Column j is kept with probability threshold[j] and otherwise redirected to alias[j]
+fn alias_table(probs: &[f64]) -> (Vec<f64>, Vec<usize>) {
+    let n = probs.len();
+    let total: f64 = probs.iter().map(|p| p.max(0.0)).sum();
+    let mut scaled: Vec<f64> = probs.iter().map(|p| p.max(0.0) * n as f64 / total).collect();
+    let mut alias: Vec<usize> = (0..n).collect();
+
+    let (mut small, mut large): (Vec<usize>, Vec<usize>) = (0..n).partition(|&i| scaled[i] < 1.0);
+    while let (Some(&s), Some(&l)) = (small.last(), large.last()) {
+        small.pop();
+        alias[s] = l;
+        scaled[l] -= 1.0 - scaled[s];
+        if scaled[l] < 1.0 {
+            large.pop();
+            small.push(l);
+        }
+    }
+    // Leftovers are 1 up to rounding error
+    for i in small.into_iter().chain(large) {
+        scaled[i] = 1.0;
+    }
+
+    (scaled, alias)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_simulator_creation() {
+        let sim = QuantumSimulator::new(2);
+        let (trace, purity) = sim.get_metrics();
+        assert_relative_eq!(trace, 1.0, epsilon = 1e-10);
+        assert_relative_eq!(purity, 1.0, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_gate_application() {
+        let mut sim = QuantumSimulator::new(1);
+        sim.apply_gate("PauliX", &[0], &[]).unwrap();
         
         // After X gate on |0⟩, should be in |1⟩
         let probs = sim.get_state().probabilities();
@@ -210,4 +1411,1082 @@ mod tests {
         assert_relative_eq!(probs[0], 0.5, epsilon = 1e-10);
         assert_relative_eq!(probs[1], 0.5, epsilon = 1e-10);
     }
+
+    #[test]
+    fn test_coherence_decay_under_dephasing() {
+        let sim = QuantumSimulator::new(2);
+        let curve = sim.coherence_decay(1, ChannelKind::Dephasing, 0.1, 30);
+
+        assert_eq!(curve.len(), 31);
+        assert_relative_eq!(curve[0], 1.0, epsilon = 1e-10);
+        for pair in curve.windows(2) {
+            assert!(pair[1] < pair[0]);
+        }
+        // Each step scales coherence by (1 - 2λ)
+        assert_relative_eq!(curve[30], 0.8_f64.powi(30), epsilon = 1e-10);
+        assert!(curve[30] < 1e-2);
+    }
+
+    #[test]
+    fn test_gate_then_inverse_restores_state() {
+        let mut sim = QuantumSimulator::new(2);
+        sim.apply_gate("Hadamard", &[0], &[]).unwrap();
+        sim.apply_gate("RY", &[1], &[0.4]).unwrap();
+        let before = sim.get_state().matrix.clone();
+
+        let gates: [(&str, Vec<usize>, Vec<f64>); 4] = [
+            ("RX", vec![0], vec![0.7]),
+            ("RZ", vec![1], vec![-1.3]),
+            ("CNOT", vec![0, 1], vec![]),
+            ("PauliY", vec![1], vec![]),
+        ];
+        for (name, wires, params) in &gates {
+            sim.apply_gate(name, wires, params).unwrap();
+            sim.apply_gate_inverse(name, wires, params).unwrap();
+        }
+
+        let after = &sim.get_state().matrix;
+        for i in 0..4 {
+            for j in 0..4 {
+                assert_relative_eq!((after[(i, j)] - before[(i, j)]).norm(), 0.0, epsilon = 1e-10);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dfs_protects_against_collective_dephasing() {
+        let mut reference = QuantumSimulator::new(2);
+        reference.apply_gate("RY", &[0], &[0.7]).unwrap();
+        reference.apply_gate("RZ", &[0], &[0.3]).unwrap();
+
+        let mut encoded = QuantumSimulator::new(2);
+        encoded.apply_gate("RY", &[0], &[0.7]).unwrap();
+        encoded.apply_gate("RZ", &[0], &[0.3]).unwrap();
+        encoded.encode_dfs(0).unwrap();
+        encoded.apply_collective_dephasing(&[0, 1], 0.5);
+        encoded.decode_dfs(0).unwrap();
+
+        let mut bare = QuantumSimulator::new(2);
+        bare.apply_gate("RY", &[0], &[0.7]).unwrap();
+        bare.apply_gate("RZ", &[0], &[0.3]).unwrap();
+        bare.apply_collective_dephasing(&[0], 0.5);
+
+        let ideal = &reference.get_state().matrix;
+        let protected_error = (&encoded.get_state().matrix - ideal).norm();
+        let bare_error = (&bare.get_state().matrix - ideal).norm();
+        assert_relative_eq!(protected_error, 0.0, epsilon = 1e-10);
+        assert!(bare_error > 0.1);
+        assert!(encoded.encode_dfs(1).is_err());
+    }
+
+    #[test]
+    fn test_measure_deterministic() {
+        let mut sim = QuantumSimulator::new(3);
+        sim.apply_gate("PauliX", &[0], &[]).unwrap();
+        sim.apply_gate("PauliX", &[2], &[]).unwrap();
+        assert_eq!(sim.measure_deterministic(1.0 - 1e-9), Some(vec![1, 0, 1]));
+
+        let mut plus = QuantumSimulator::new(1);
+        plus.apply_gate("Hadamard", &[0], &[]).unwrap();
+        assert_eq!(plus.measure_deterministic(1.0 - 1e-9), None);
+    }
+
+    #[test]
+    fn test_gate_unitary_matches_known_matrices() {
+        let sim = QuantumSimulator::new(2);
+        let one = Complex::new(1.0, 0.0);
+        let zero = Complex::new(0.0, 0.0);
+
+        // CNOT(0 → 1) swaps |10⟩ and |11⟩
+        let cnot = sim.gate_unitary("CNOT", &[0, 1], &[]).unwrap();
+        let expected = DMatrix::from_row_slice(4, 4, &[
+            one, zero, zero, zero,
+            zero, one, zero, zero,
+            zero, zero, zero, one,
+            zero, zero, one, zero,
+        ]);
+        assert_eq!(cnot, expected);
+
+        // CNOT(1 → 0) swaps |01⟩ and |11⟩ instead
+        let reversed = sim.gate_unitary("CX", &[1, 0], &[]).unwrap();
+        assert_eq!(reversed[(3, 1)], one);
+        assert_eq!(reversed[(1, 3)], one);
+
+        // Diagonal and single-wire gates are expanded to the full register too
+        let rz = sim.gate_unitary("RZ", &[1], &[0.4]).unwrap();
+        assert_relative_eq!((rz - kron(&identity(), &crate::gates::rz(0.4))).norm(), 0.0, epsilon = 1e-12);
+        let h = sim.gate_unitary("H", &[0], &[]).unwrap();
+        assert_relative_eq!((h - kron(&hadamard(), &identity())).norm(), 0.0, epsilon = 1e-12);
+        assert!(sim.gate_unitary("CNOT", &[0, 2], &[]).is_err());
+    }
+
+    #[test]
+    fn test_rzz_entangling_phase() {
+        let mut sim = QuantumSimulator::new(2);
+        sim.apply_gate("RZZ", &[0, 1], &[std::f64::consts::FRAC_PI_2]).unwrap();
+        // |00⟩ only picks up a global phase
+        assert_relative_eq!(sim.get_state().probabilities()[0], 1.0, epsilon = 1e-10);
+
+        // On |++⟩, RZZ(π/2) maps the stabilizer XI to YZ while XX commutes through
+        sim.reset();
+        sim.apply_gate("H", &[0], &[]).unwrap();
+        sim.apply_gate("H", &[1], &[]).unwrap();
+        sim.apply_gate("RZZ", &[0, 1], &[std::f64::consts::FRAC_PI_2]).unwrap();
+        assert_relative_eq!(sim.expectation_pauli("XI").unwrap(), 0.0, epsilon = 1e-10);
+        assert_relative_eq!(sim.expectation_pauli("YZ").unwrap(), 1.0, epsilon = 1e-10);
+        assert_relative_eq!(sim.expectation_pauli("XX").unwrap(), 1.0, epsilon = 1e-10);
+        assert!(sim.apply_gate("RXX", &[0], &[0.1]).is_err());
+        assert!(sim.apply_gate("Canonical", &[0, 1], &[0.1]).is_err());
+        assert!(sim.apply_gate("XYZ", &[0, 1], &[0.1, 0.2, 0.3]).is_ok());
+    }
+
+    #[test]
+    fn test_readout_mitigated_expectation_is_closer() {
+        let mut sim = QuantumSimulator::new(2);
+        sim.set_seed(7);
+        sim.apply_gate("RY", &[0], &[0.6]).unwrap();
+        let exact = sim.expectation_pauli("ZI").unwrap();
+
+        let err = ReadoutError::new(0.08, 0.15);
+        let cal = err.confusion_matrix(2);
+        let raw: HashMap<String, f64> = sim
+            .measure_counts_with_readout_error(50_000, &err)
+            .into_iter()
+            .map(|(k, v)| (k, v as f64))
+            .collect();
+        let unmitigated = parity_expectation(&raw, &[0]);
+        let mitigated = sim.mitigated_expectation_z(&[0], 50_000, &err, &cal).unwrap();
+
+        assert!((mitigated - exact).abs() < (unmitigated - exact).abs());
+        assert!((mitigated - exact).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_mutual_information() {
+        let mut bell = QuantumSimulator::new(3);
+        bell.apply_gate("H", &[0], &[]).unwrap();
+        bell.apply_gate("CNOT", &[0, 2], &[]).unwrap();
+        assert_relative_eq!(bell.mutual_information(0, 2).unwrap(), 2.0, epsilon = 1e-9);
+
+        let mut product = QuantumSimulator::new(2);
+        product.apply_gate("H", &[0], &[]).unwrap();
+        product.apply_gate("RY", &[1], &[0.8]).unwrap();
+        assert_relative_eq!(product.mutual_information(0, 1).unwrap(), 0.0, epsilon = 1e-9);
+        assert!(product.mutual_information(1, 1).is_err());
+    }
+
+    #[test]
+    fn test_cphase_fraction() {
+        // k = 1 is a phase of π, i.e. CZ
+        let mut sim = QuantumSimulator::new(2);
+        sim.apply_gate("H", &[0], &[]).unwrap();
+        sim.apply_gate("H", &[1], &[]).unwrap();
+        let mut reference = DensityMatrix::new(2);
+        reference.apply_unitary(&build_single_qubit_unitary(&hadamard(), 0, 2));
+        reference.apply_unitary(&build_single_qubit_unitary(&hadamard(), 1, 2));
+        reference.apply_unitary(&cz());
+        sim.apply_gate("CPhaseFraction", &[0, 1], &[1.0]).unwrap();
+        assert_relative_eq!((&sim.get_state().matrix - &reference.matrix).norm(), 0.0, epsilon = 1e-12);
+        assert!(sim.apply_gate("CPhaseFraction", &[0, 1], &[1.5]).is_err());
+
+        // Dropping the k = 3 term of a 3-qubit QFT perturbs, but does not destroy, the output
+        let prepare = |sim: &mut QuantumSimulator| {
+            sim.apply_gate("PauliX", &[2], &[]).unwrap();
+            sim.apply_gate("RY", &[1], &[0.9]).unwrap();
+        };
+        let mut exact = QuantumSimulator::new(3);
+        prepare(&mut exact);
+        exact.run_circuit(&Circuit::qft(&[0, 1, 2])).unwrap();
+        let mut truncated = QuantumSimulator::new(3);
+        prepare(&mut truncated);
+        truncated.run_circuit(&Circuit::approximate_qft(&[0, 1, 2], 2)).unwrap();
+
+        let overlap = (&exact.get_state().matrix * &truncated.get_state().matrix).trace().re;
+        assert!(overlap > 0.8 && overlap < 0.999);
+    }
+
+    #[test]
+    fn test_spectator_noise_only_hits_idle_qubits() {
+        let mut sim = QuantumSimulator::new(3);
+        for w in 0..3 {
+            sim.apply_gate("H", &[w], &[]).unwrap();
+        }
+        sim.apply_gate_with_spectator_noise("RZ", &[1], &[0.4], ChannelKind::Dephasing, 0.3)
+            .unwrap();
+
+        let purity = |w: usize| sim.get_state().partial_trace(&[w]).unwrap().purity();
+        assert_relative_eq!(purity(1), 1.0, epsilon = 1e-10);
+        assert!(purity(0) < 0.99);
+        assert!(purity(2) < 0.99);
+        assert_relative_eq!(purity(0), purity(2), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_trace_distance_to_initial() {
+        let mut sim = QuantumSimulator::new(2);
+        assert_relative_eq!(sim.trace_distance_to_initial(), 0.0, epsilon = 1e-12);
+
+        sim.apply_gate("PauliX", &[1], &[]).unwrap();
+        assert_relative_eq!(sim.trace_distance_to_initial(), 1.0, epsilon = 1e-12);
+
+        // |+⟩ vs |0⟩: D = sqrt(1 - |⟨+|0⟩|²) = 1/√2
+        let mut plus = QuantumSimulator::new(1);
+        plus.apply_gate("H", &[0], &[]).unwrap();
+        assert_relative_eq!(plus.trace_distance_to_initial(), 0.5_f64.sqrt(), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_noise_and_measurement_seeds_are_independent() {
+        let run = |noise_seed: u64, measurement_seed: u64| {
+            let mut sim = QuantumSimulator::new(3);
+            sim.set_noise_seed(noise_seed);
+            sim.set_measurement_seed(measurement_seed);
+            for wire in 0..3 {
+                sim.apply_gate("RY", &[wire], &[0.9 + 0.3 * wire as f64]).unwrap();
+            }
+            let injected = sim.inject_random_errors(0.5);
+            (injected, sim.get_state().matrix.clone(), sim.measure_shots(50))
+        };
+
+        let (errors_a, rho_a, shots_a) = run(4, 100);
+        let (errors_b, rho_b, shots_b) = run(4, 200);
+        assert_eq!(errors_a, errors_b);
+        assert_eq!(rho_a, rho_b);
+        assert_ne!(shots_a, shots_b);
+
+        // Same measurement seed replays the shots when the states agree
+        let (_, _, shots_c) = run(4, 100);
+        assert_eq!(shots_a, shots_c);
+    }
+
+    #[test]
+    fn test_trace_monitor_stays_within_tolerance() {
+        let mut circuit = Circuit::new();
+        circuit
+            .add_gate("Hadamard", &[0], &[])
+            .add_gate("CNOT", &[0, 1], &[])
+            .add_gate("RY", &[2], &[0.4])
+            .add_gate("CNOT", &[1, 2], &[])
+            .add_gate("RZ", &[0], &[1.1]);
+        let noise = NoiseModel {
+            single_qubit_error: 0.02,
+            two_qubit_error: 0.05,
+            idle_gamma: 0.03,
+            idle_lambda: 0.01,
+            ..NoiseModel::noiseless()
+        };
+
+        let mut sim = QuantumSimulator::new(3);
+        let (traces, drifted) = sim.run_with_trace_monitor(&circuit, &noise).unwrap();
+        assert_eq!(traces.len(), circuit.depth());
+        assert!(!drifted);
+        for trace in traces {
+            assert_relative_eq!(trace, 1.0, epsilon = 1e-10);
+        }
+
+        // Same state as the unmonitored run
+        let mut plain = QuantumSimulator::new(3);
+        plain.run_circuit_noisy(&circuit, &noise).unwrap();
+        assert_relative_eq!((&plain.get_state().matrix - &sim.get_state().matrix).norm(), 0.0, epsilon = 1e-14);
+    }
+
+    #[test]
+    fn test_run_with_gate_noise_per_gate() {
+        let depolarizing = Channel::from_kind(ChannelKind::Depolarizing, 0.1);
+        let op = |name: &str, wires: &[usize], channel: Option<Channel>| -> NoisyOp {
+            (name.to_string(), wires.to_vec(), vec![], channel)
+        };
+
+        let mut clean = QuantumSimulator::new(2);
+        clean
+            .run_with_gate_noise(&[op("Hadamard", &[0], None), op("CNOT", &[0, 1], None)])
+            .unwrap();
+        assert_relative_eq!(clean.get_state().purity(), 1.0, epsilon = 1e-12);
+
+        let mut noisy = QuantumSimulator::new(2);
+        noisy
+            .run_with_gate_noise(&[
+                op("Hadamard", &[0], None),
+                op("CNOT", &[0, 1], Some(depolarizing.clone())),
+            ])
+            .unwrap();
+        assert!(noisy.get_state().purity() < 0.9);
+
+        // Equivalent to applying the channel by hand on both CNOT wires
+        let mut manual = QuantumSimulator::new(2);
+        manual.apply_gate("Hadamard", &[0], &[]).unwrap();
+        manual.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        manual.apply_depolarizing(0, 0.1);
+        manual.apply_depolarizing(1, 0.1);
+        assert_relative_eq!(
+            (&noisy.get_state().matrix - &manual.get_state().matrix).norm(),
+            0.0,
+            epsilon = 1e-12
+        );
+
+        let two_qubit = Channel::new(vec![DMatrix::identity(4, 4)]);
+        assert!(clean.run_with_gate_noise(&[op("CNOT", &[0, 1], Some(two_qubit.clone()))]).is_err());
+
+        // A rejected channel anywhere in the list leaves the state as it was
+        let before = clean.get_state().matrix.clone();
+        let result = clean.run_with_gate_noise(&[
+            op("Hadamard", &[1], None),
+            op("CNOT", &[0, 1], Some(two_qubit)),
+        ]);
+        assert!(matches!(result, Err(QsimError::InvalidParameter(_))));
+        assert_eq!(clean.get_state().matrix, before);
+    }
+
+    #[test]
+    fn test_phase_gates_are_exact_on_bell_state() {
+        // Entries are written out, not derived from RZ, so there is no rounding drift
+        assert_eq!(s_gate()[(1, 1)], Complex::new(0.0, 1.0));
+        assert_eq!(s_dagger()[(1, 1)], Complex::new(0.0, -1.0));
+        assert_eq!(t_gate()[(0, 0)], Complex::new(1.0, 0.0));
+
+        // (|00⟩ + i|11⟩)/√2 is the +1 eigenstate of XY
+        let mut sim = QuantumSimulator::new(2);
+        sim.apply_gate("Hadamard", &[0], &[]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        sim.apply_gate("S", &[0], &[]).unwrap();
+        assert_relative_eq!(sim.expectation_pauli("XY").unwrap(), 1.0, epsilon = 1e-12);
+
+        // Sdg undoes S; then T on one wire and Tdg on the other cancel on |11⟩
+        sim.apply_gate("Sdg", &[0], &[]).unwrap();
+        sim.apply_gate("T", &[1], &[]).unwrap();
+        assert_relative_eq!(sim.expectation_pauli("XX").unwrap(), std::f64::consts::FRAC_1_SQRT_2, epsilon = 1e-12);
+        sim.apply_gate("Tdg", &[0], &[]).unwrap();
+        assert_relative_eq!(sim.expectation_pauli("XX").unwrap(), 1.0, epsilon = 1e-12);
+        assert!(sim.apply_gate("T", &[0, 1], &[]).is_err());
+    }
+
+    #[test]
+    fn test_most_likely_outcome() {
+        let mut sim = QuantumSimulator::new(3);
+        sim.apply_gate("RY", &[0], &[2.6]).unwrap();
+        sim.apply_gate("RY", &[1], &[0.5]).unwrap();
+        sim.apply_gate("RY", &[2], &[2.4]).unwrap();
+
+        let (bits, p) = sim.most_likely_outcome();
+        assert_eq!(bits, vec![1, 0, 1]);
+        let expected = (1.3f64.sin() * 0.25f64.cos() * 1.2f64.sin()).powi(2);
+        assert_relative_eq!(p, expected, epsilon = 1e-12);
+        assert!(p < 0.9);
+    }
+
+    #[test]
+    fn test_swap_exchanges_reduced_states() {
+        let mut sim = QuantumSimulator::new(3);
+        sim.apply_gate("RY", &[0], &[0.8]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        sim.apply_gate("RX", &[1], &[0.5]).unwrap();
+        let before: Vec<DMatrix<Complex<f64>>> =
+            (0..3).map(|w| sim.get_state().partial_trace(&[w]).unwrap().matrix).collect();
+
+        // Non-adjacent wires 0 and 2, then 1 with itself (identity)
+        sim.apply_gate("SWAP", &[0, 2], &[]).unwrap();
+        sim.apply_gate("SWAP", &[1, 1], &[]).unwrap();
+        let after: Vec<DMatrix<Complex<f64>>> =
+            (0..3).map(|w| sim.get_state().partial_trace(&[w]).unwrap().matrix).collect();
+
+        assert_relative_eq!((&after[0] - &before[2]).norm(), 0.0, epsilon = 1e-12);
+        assert_relative_eq!((&after[2] - &before[0]).norm(), 0.0, epsilon = 1e-12);
+        assert_relative_eq!((&after[1] - &before[1]).norm(), 0.0, epsilon = 1e-12);
+        assert!((&before[0] - &before[2]).norm() > 0.1);
+        assert!(sim.apply_gate("SWAP", &[0], &[]).is_err());
+    }
+
+    #[test]
+    fn test_controlled_gates() {
+        // CZ on |1⟩|+⟩ puts the -1 phase on |11⟩, giving |1⟩|−⟩
+        let mut sim = QuantumSimulator::new(2);
+        sim.apply_gate("PauliX", &[0], &[]).unwrap();
+        sim.apply_gate("Hadamard", &[1], &[]).unwrap();
+        sim.apply_gate("CZ", &[0, 1], &[]).unwrap();
+        assert_relative_eq!(sim.expectation_pauli("IX").unwrap(), -1.0, epsilon = 1e-12);
+
+        // CRX(π) is controlled-(-iX), with control above target and non-adjacent wires
+        let sim = QuantumSimulator::new(3);
+        let crx = sim.gate_unitary("CRX", &[2, 0], &[std::f64::consts::PI]).unwrap();
+        let minus_i_x = pauli_x() * Complex::new(0.0, -1.0);
+        assert_relative_eq!((crx - build_controlled_unitary(2, 0, &minus_i_x, 3)).norm(), 0.0, epsilon = 1e-12);
+
+        // Diagonal forms agree with the general builder
+        for (name, gate) in [("CRZ", rz(0.7)), ("CRY", ry(0.7))] {
+            let built = sim.gate_unitary(name, &[1, 2], &[0.7]).unwrap();
+            assert_relative_eq!((built - build_controlled_unitary(1, 2, &gate, 3)).norm(), 0.0, epsilon = 1e-12);
+        }
+        let cphase = sim.gate_unitary("CPhase", &[0, 2], &[0.4]).unwrap();
+        assert_relative_eq!((cphase - build_cphase_unitary(0, 2, 0.4, 3)).norm(), 0.0, epsilon = 1e-12);
+
+        assert!(sim.gate_unitary("CRX", &[0, 1], &[]).is_err());
+        assert!(sim.gate_unitary("CZ", &[1, 1], &[]).is_err());
+    }
+
+    #[test]
+    fn test_toffoli_needs_both_controls() {
+        for input in 0..8usize {
+            let bits = [(input >> 2) & 1, (input >> 1) & 1, input & 1];
+            let mut sim = QuantumSimulator::new(3);
+            for (wire, &bit) in bits.iter().enumerate() {
+                if bit == 1 {
+                    sim.apply_gate("PauliX", &[wire], &[]).unwrap();
+                }
+            }
+            // Controls on wires 2 and 0, target on wire 1
+            sim.apply_gate("Toffoli", &[2, 0, 1], &[]).unwrap();
+
+            let mut expected = bits.to_vec();
+            if bits[0] == 1 && bits[2] == 1 {
+                expected[1] ^= 1;
+            }
+            assert_eq!(sim.measure_deterministic(1.0 - 1e-9), Some(expected));
+        }
+
+        let sim = QuantumSimulator::new(3);
+        assert!(sim.gate_unitary("CCX", &[0, 0, 1], &[]).is_err());
+        assert!(sim.gate_unitary("CCX", &[0, 1], &[]).is_err());
+    }
+
+    #[test]
+    fn test_z_correlators_on_ghz() {
+        let mut sim = QuantumSimulator::new(4);
+        sim.apply_gate("Hadamard", &[0], &[]).unwrap();
+        for wire in 1..4 {
+            sim.apply_gate("CNOT", &[0, wire], &[]).unwrap();
+        }
+        let (singles, pairs) = sim.z_correlators();
+        assert_eq!(singles.len(), 4);
+        assert!(singles.iter().all(|z| z.abs() < 1e-12));
+        assert_relative_eq!((pairs - DMatrix::from_element(4, 4, 1.0)).norm(), 0.0, epsilon = 1e-12);
+
+        // Product state: ⟨ZᵢZⱼ⟩ = ⟨Zᵢ⟩⟨Zⱼ⟩
+        let mut product = QuantumSimulator::new(3);
+        product.apply_gate("RY", &[0], &[0.6]).unwrap();
+        product.apply_gate("RX", &[2], &[1.3]).unwrap();
+        let (singles, pairs) = product.z_correlators();
+        assert_relative_eq!(singles[0], 0.6f64.cos(), epsilon = 1e-12);
+        assert_relative_eq!(pairs[(0, 2)], 0.6f64.cos() * 1.3f64.cos(), epsilon = 1e-12);
+        assert_relative_eq!(pairs[(2, 1)], 1.3f64.cos(), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_inject_random_errors() {
+        let mut sim = QuantumSimulator::new(4);
+        sim.set_seed(13);
+        sim.apply_gate("H", &[0], &[]).unwrap();
+        sim.apply_gate("RY", &[2], &[0.7]).unwrap();
+        let original = sim.get_state().matrix.clone();
+
+        assert!(sim.inject_random_errors(0.0).is_empty());
+        assert_eq!(sim.get_state().matrix, original);
+
+        let injected = sim.inject_random_errors(1.0);
+        assert_eq!(injected.iter().map(|&(w, _)| w).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert!((&sim.get_state().matrix - &original).norm() > 1e-3);
+
+        // Paulis are self-inverse, so replaying the record undoes the errors
+        for (wire, pauli) in injected {
+            sim.apply_gate(&pauli.to_string(), &[wire], &[]).unwrap();
+        }
+        assert_relative_eq!((&sim.get_state().matrix - &original).norm(), 0.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_exact_counts_scale_probabilities() {
+        let mut sim = QuantumSimulator::new(2);
+        sim.apply_gate("RY", &[0], &[0.8]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+
+        let counts = sim.exact_counts(1000);
+        assert_relative_eq!(counts.values().sum::<f64>(), 1000.0, epsilon = 1e-9);
+        assert_eq!(counts.len(), 2);
+
+        let probs = sim.get_state().probabilities();
+        assert_relative_eq!(counts["00"], probs[0] * 1000.0, epsilon = 1e-9);
+        assert_relative_eq!(counts["11"], probs[3] * 1000.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_z_expectations_per_qubit() {
+        let mut sim = QuantumSimulator::new(2);
+        sim.apply_gate("PauliX", &[0], &[]).unwrap();
+        let z = sim.z_expectations();
+        assert_relative_eq!(z[0], -1.0, epsilon = 1e-12);
+        assert_relative_eq!(z[1], 1.0, epsilon = 1e-12);
+
+        let mut uniform = QuantumSimulator::new(3);
+        for w in 0..3 {
+            uniform.apply_gate("Hadamard", &[w], &[]).unwrap();
+        }
+        for v in uniform.z_expectations() {
+            assert_relative_eq!(v, 0.0, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_estimated_purity_converges() {
+        let mut sim = QuantumSimulator::new(2);
+        sim.set_seed(5);
+        sim.apply_gate("Hadamard", &[0], &[]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        sim.apply_depolarizing(0, 0.3);
+        let exact = sim.get_state().purity();
+        assert!(exact < 0.9);
+
+        let estimate = sim.estimated_purity(400, 200).unwrap();
+        assert!((estimate - exact).abs() < 0.05, "{} vs {}", estimate, exact);
+        assert!(sim.estimated_purity(10, 1).is_err());
+    }
+
+    #[test]
+    fn test_diff_report_finds_flipped_state() {
+        let zero = QuantumSimulator::new(1);
+        let mut one = QuantumSimulator::new(1);
+        one.apply_gate("PauliX", &[0], &[]).unwrap();
+
+        // |0⟩ and |1⟩ differ by 1.0 on both outcomes; the tie goes to index 0
+        let (index, diff) = zero.diff_report(&one).unwrap();
+        assert_eq!(index, 0);
+        assert_relative_eq!(diff, 1.0, epsilon = 1e-12);
+
+        let mut ghz = QuantumSimulator::new(2);
+        ghz.apply_gate("Hadamard", &[0], &[]).unwrap();
+        ghz.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        let mut noisy = QuantumSimulator::new(2);
+        noisy.apply_gate("Hadamard", &[0], &[]).unwrap();
+        noisy.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        noisy.apply_amplitude_damping(1, 0.4);
+        // Decay moves weight 0.2 from |11⟩ to |10⟩, so index 2 wins the tie
+        let (index, diff) = ghz.diff_report(&noisy).unwrap();
+        assert_eq!(index, 2);
+        assert_relative_eq!(diff, 0.2, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_drifting_noise_schedule() {
+        let mut circuit = Circuit::new();
+        for _ in 0..5 {
+            circuit.add_gate("RX", &[0], &[0.2]);
+        }
+        let mut ideal = QuantumSimulator::new(1);
+        ideal.run_circuit(&circuit).unwrap();
+        let fidelity = |sim: &QuantumSimulator| {
+            (&sim.get_state().matrix * &ideal.get_state().matrix).trace().re
+        };
+        let depolarizing = |p: f64| NoiseModel {
+            single_qubit_error: p,
+            ..NoiseModel::noiseless()
+        };
+
+        // Error rate ramps 0, 0.1, ..., 0.4 versus a constant 0.2 with the same mean
+        let mut drifting = QuantumSimulator::new(1);
+        drifting
+            .run_circuit_with_schedule(&circuit, |layer| depolarizing(0.1 * layer as f64))
+            .unwrap();
+        let mut constant = QuantumSimulator::new(1);
+        constant.run_circuit_noisy(&circuit, &depolarizing(0.2)).unwrap();
+
+        // Bloch vector shrinks by Π(1 - pᵢ) = 0.3024 versus 0.8⁵ = 0.32768
+        assert_relative_eq!(fidelity(&drifting), 0.5 * (1.0 + 0.3024), epsilon = 1e-10);
+        assert_relative_eq!(fidelity(&constant), 0.5 * (1.0 + 0.32768), epsilon = 1e-10);
+        assert!(fidelity(&drifting) < fidelity(&constant));
+    }
+
+    #[test]
+    fn test_noise_placement_matters_for_cnot() {
+        // Local depolarizing does not commute with CNOT: an X on the control before the gate
+        // spreads to both qubits, so Before and After give different states
+        let run = |placement: NoisePlacement| {
+            let noise = NoiseModel {
+                two_qubit_error: 0.2,
+                placement,
+                ..NoiseModel::noiseless()
+            };
+            let mut sim = QuantumSimulator::new(2);
+            sim.apply_gate("Hadamard", &[0], &[]).unwrap();
+            sim.apply_gate_noisy("CNOT", &[0, 1], &[], &noise).unwrap();
+            sim.get_state().clone()
+        };
+
+        let before = run(NoisePlacement::Before);
+        let after = run(NoisePlacement::After);
+        let both = run(NoisePlacement::Both);
+        assert!(before.trace_distance(&after).unwrap() > 1e-3);
+        assert!(both.purity() < before.purity());
+        assert!(both.purity() < after.purity());
+        assert_eq!(NoiseModel::noiseless().placement, NoisePlacement::After);
+    }
+
+    #[test]
+    fn test_shot_iterator_streams_seeded_shots() {
+        let mut sim = QuantumSimulator::new(2);
+        sim.apply_gate("RY", &[0], &[1.2]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+
+        sim.set_seed(3);
+        let first: Vec<Vec<usize>> = sim.shot_iterator(50).collect();
+        sim.set_seed(3);
+        let second: Vec<Vec<usize>> = sim.shot_iterator(50).collect();
+        assert_eq!(first, second);
+        assert_eq!(sim.shot_iterator(7).len(), 7);
+
+        let n = 20_000;
+        let ones = sim.shot_iterator(n).filter(|bits| bits == &[1, 1]).count();
+        let p11 = sim.get_state().probabilities()[3];
+        assert!((ones as f64 / n as f64 - p11).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_fast_counts_match_distribution_and_seed() {
+        let mut sim = QuantumSimulator::new(3);
+        sim.apply_gate("RY", &[0], &[0.7]).unwrap();
+        sim.apply_gate("RY", &[1], &[2.1]).unwrap();
+        sim.apply_gate("CNOT", &[1, 2], &[]).unwrap();
+
+        let n = 100_000;
+        let counts = sim.measure_counts_fast(n, 42);
+        assert_eq!(counts, sim.measure_counts_fast(n, 42));
+        assert_eq!(counts.values().sum::<usize>(), n);
+
+        for (index, p) in sim.get_state().probabilities().into_iter().enumerate() {
+            let observed = counts.get(&index_to_bitstring(index, 3)).copied().unwrap_or(0);
+            assert!((observed as f64 / n as f64 - p).abs() < 0.01, "index {}", index);
+        }
+    }
+
+    #[test]
+    fn test_stabilizer_state_preparation() {
+        let stabilizers = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let mut sim = QuantumSimulator::new(2);
+        sim.prepare_stabilizer_state(&stabilizers(&["XX", "ZZ"])).unwrap();
+        let rho = &sim.get_state().matrix;
+        for (i, j) in [(0, 0), (0, 3), (3, 0), (3, 3)] {
+            assert_relative_eq!(rho[(i, j)].re, 0.5, epsilon = 1e-12);
+        }
+        assert_relative_eq!(sim.get_state().purity(), 1.0, epsilon = 1e-12);
+
+        // A single stabilizer leaves a mixture over its +1 eigenspace
+        sim.prepare_stabilizer_state(&stabilizers(&["ZZ"])).unwrap();
+        assert_relative_eq!(sim.get_state().purity(), 0.5, epsilon = 1e-12);
+        assert_relative_eq!(sim.expectation_pauli("ZZ").unwrap(), 1.0, epsilon = 1e-12);
+
+        assert!(sim.prepare_stabilizer_state(&stabilizers(&["XI", "ZI"])).is_err());
+        assert!(sim.prepare_stabilizer_state(&stabilizers(&["XX", "YY", "ZZ"])).is_err());
+        assert!(sim.prepare_stabilizer_state(&stabilizers(&["ZZ", "ZZI"])).is_err());
+        assert!(sim.prepare_stabilizer_state(&stabilizers(&["II"])).is_err());
+    }
+
+    #[test]
+    fn test_measure_qubit_keep_state_collapses() {
+        let mut sim = QuantumSimulator::new(2);
+        sim.set_seed(9);
+        sim.apply_gate("Hadamard", &[0], &[]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+
+        let (bit, post) = sim.measure_qubit_keep_state(0).unwrap();
+        let marginal = post.partial_trace(&[0]).unwrap();
+        assert_relative_eq!(marginal.matrix[(bit, bit)].re, 1.0, epsilon = 1e-12);
+        assert_relative_eq!(post.trace().re, 1.0, epsilon = 1e-12);
+        // The Bell partner collapses with it, and the simulator holds the same state
+        assert_relative_eq!(post.partial_trace(&[1]).unwrap().matrix[(bit, bit)].re, 1.0, epsilon = 1e-12);
+        assert_eq!(sim.get_state().matrix, post.matrix);
+
+        // Measuring again repeats the outcome
+        assert_eq!(sim.measure_qubit_keep_state(0).unwrap().0, bit);
+        assert!(sim.measure_qubit_keep_state(2).is_err());
+    }
+
+    #[test]
+    fn test_assert_physical_reports_violations() {
+        let mut sim = QuantumSimulator::new(2);
+        sim.apply_gate("Hadamard", &[0], &[]).unwrap();
+        sim.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        sim.apply_amplitude_damping(1, 0.3);
+        sim.apply_depolarizing(0, 0.2);
+        assert!(sim.assert_physical(1e-10).is_ok());
+
+        let valid = sim.state.clone();
+        sim.state.matrix[(0, 0)] += Complex::new(0.1, 0.0);
+        assert!(matches!(
+            sim.assert_physical(1e-10),
+            Err(QsimError::Unphysical(msg)) if msg.contains("Trace")
+        ));
+
+        sim.state = valid.clone();
+        sim.state.matrix[(0, 3)] += Complex::new(0.0, 0.2);
+        assert!(matches!(
+            sim.assert_physical(1e-10),
+            Err(QsimError::Unphysical(msg)) if msg.contains("Hermitian")
+        ));
+
+        // Unit trace and Hermitian, but with a negative eigenvalue
+        sim.state = valid;
+        sim.state.matrix[(0, 3)] = Complex::new(0.9, 0.0);
+        sim.state.matrix[(3, 0)] = Complex::new(0.9, 0.0);
+        assert!(matches!(
+            sim.assert_physical(1e-10),
+            Err(QsimError::Unphysical(msg)) if msg.contains("positive semidefinite")
+        ));
+    }
+
+    #[test]
+    fn test_controlled_bell_preparation() {
+        let mut bell = Circuit::new();
+        bell.add_gate("Hadamard", &[1], &[]).add_gate("CNOT", &[1, 2], &[]);
+        let reduced_purity = |sim: &QuantumSimulator| sim.get_state().partial_trace(&[1]).unwrap().purity();
+
+        let mut off = QuantumSimulator::new(3);
+        off.apply_controlled_circuit(0, &bell).unwrap();
+        assert_relative_eq!(off.get_state().matrix[(0, 0)].re, 1.0, epsilon = 1e-12);
+        assert_relative_eq!(reduced_purity(&off), 1.0, epsilon = 1e-12);
+
+        let mut on = QuantumSimulator::new(3);
+        on.apply_gate("PauliX", &[0], &[]).unwrap();
+        on.apply_controlled_circuit(0, &bell).unwrap();
+        assert_relative_eq!(reduced_purity(&on), 0.5, epsilon = 1e-12);
+        assert_relative_eq!(on.expectation_pauli("IXX").unwrap(), 1.0, epsilon = 1e-12);
+
+        let mut touches_control = Circuit::new();
+        touches_control.add_gate("CNOT", &[0, 1], &[]);
+        assert!(on.apply_controlled_circuit(0, &touches_control).is_err());
+    }
+
+    #[test]
+    fn test_bell_witness_detects_entanglement() {
+        // W = I/2 - |Φ+⟩⟨Φ+| = (II - XX + YY - ZZ) / 4
+        let witness: Vec<(f64, String)> = [(0.25, "II"), (-0.25, "XX"), (0.25, "YY"), (-0.25, "ZZ")]
+            .iter()
+            .map(|&(c, p)| (c, p.to_string()))
+            .collect();
+
+        let mut bell = QuantumSimulator::new(2);
+        bell.apply_gate("Hadamard", &[0], &[]).unwrap();
+        bell.apply_gate("CNOT", &[0, 1], &[]).unwrap();
+        assert_relative_eq!(bell.evaluate_witness(&witness).unwrap(), -0.5, epsilon = 1e-12);
+
+        let mut product = QuantumSimulator::new(2);
+        product.apply_gate("Hadamard", &[0], &[]).unwrap();
+        product.apply_gate("RY", &[1], &[0.4]).unwrap();
+        assert!(product.evaluate_witness(&witness).unwrap() >= 0.0);
+        assert!(product.evaluate_witness(&[(1.0, "Q".to_string())]).is_err());
+    }
+
+    #[test]
+    fn test_amplitude_damping_layer_per_qubit() {
+        let mut sim = QuantumSimulator::new(3);
+        for w in 0..3 {
+            sim.apply_gate("PauliX", &[w], &[]).unwrap();
+        }
+        sim.apply_amplitude_damping_layer(&[0.1, 0.5, 0.0]).unwrap();
+
+        // Excited population on wire w is (1 - ⟨Zw⟩) / 2 = 1 - gamma_w
+        let excited: Vec<f64> = sim.z_expectations().iter().map(|z| 0.5 * (1.0 - z)).collect();
+        assert_relative_eq!(excited[0], 0.9, epsilon = 1e-12);
+        assert_relative_eq!(excited[1], 0.5, epsilon = 1e-12);
+        assert_relative_eq!(excited[2], 1.0, epsilon = 1e-12);
+        assert!(sim.apply_amplitude_damping_layer(&[0.1, 0.2]).is_err());
+    }
+
+    #[test]
+    fn test_sampled_pauli_expectation_on_eigenstates() {
+        let mut plus = QuantumSimulator::new(1);
+        plus.set_seed(2);
+        plus.apply_gate("Hadamard", &[0], &[]).unwrap();
+        assert_relative_eq!(plus.sampled_pauli_expectation("X", 1_000).unwrap(), 1.0, epsilon = 1e-12);
+        assert!(plus.sampled_pauli_expectation("Z", 20_000).unwrap().abs() < 0.03);
+
+        // RX(-π/2)|0⟩ is the +1 eigenstate of Y; the idle wire is ignored
+        let mut sim = QuantumSimulator::new(2);
+        sim.set_seed(3);
+        sim.apply_gate("RX", &[0], &[-std::f64::consts::FRAC_PI_2]).unwrap();
+        sim.apply_gate("RY", &[1], &[0.7]).unwrap();
+        assert_relative_eq!(sim.sampled_pauli_expectation("YI", 1_000).unwrap(), 1.0, epsilon = 1e-12);
+        let z1 = sim.sampled_pauli_expectation("IZ", 50_000).unwrap();
+        assert!((z1 - 0.7f64.cos()).abs() < 0.02, "{}", z1);
+        assert!(sim.sampled_pauli_expectation("Q", 10).is_err());
+    }
+
+    #[test]
+    fn test_run_and_measure_converges_with_shots() {
+        let mut circuit = Circuit::new();
+        circuit
+            .add_gate("RY", &[0], &[0.9])
+            .add_gate("CNOT", &[0, 1], &[])
+            .add_gate("RX", &[1], &[0.3]);
+        let noise = NoiseModel {
+            single_qubit_error: 0.02,
+            two_qubit_error: 0.05,
+            ..NoiseModel::noiseless()
+        };
+
+        let errors: Vec<f64> = [100, 200_000]
+            .iter()
+            .map(|&shots| {
+                let mut sim = QuantumSimulator::new(2);
+                sim.set_seed(31);
+                let (exact, sampled) = sim.run_and_measure(&circuit, &noise, "XX", shots).unwrap();
+                assert!(exact.abs() > 0.1);
+                (sampled - exact).abs()
+            })
+            .collect();
+        assert!(errors[1] < 0.01);
+        assert!(errors[1] < errors[0]);
+    }
+
+    #[test]
+    fn test_apply_gate_error_variants() {
+        let mut sim = QuantumSimulator::new(2);
+        assert!(matches!(
+            sim.apply_gate("Foo", &[0], &[]),
+            Err(QsimError::UnknownGate(name)) if name == "Foo"
+        ));
+        assert_eq!(
+            sim.apply_gate("CNOT", &[0], &[]),
+            Err(QsimError::WrongWireCount { expected: 2, got: 1 })
+        );
+        assert_eq!(sim.apply_gate("PauliX", &[5], &[]), Err(QsimError::WireOutOfRange(5)));
+        assert!(matches!(sim.apply_gate("RX", &[0], &[]), Err(QsimError::InvalidParameter(_))));
+        assert_eq!(
+            QsimError::WrongWireCount { expected: 2, got: 1 }.to_string(),
+            "Gate requires 2 wire(s), got 1"
+        );
+    }
+
+    #[test]
+    fn test_cz_wire_order_is_irrelevant() {
+        let prepare = |wires: &[usize]| {
+            let mut sim = QuantumSimulator::new(3);
+            for w in 0..3 {
+                sim.apply_gate("Hadamard", &[w], &[]).unwrap();
+            }
+            sim.apply_gate("CZ", wires, &[]).unwrap();
+            sim
+        };
+        let forward = prepare(&[0, 2]);
+        let backward = prepare(&[2, 0]);
+        let diff = &forward.get_state().matrix - &backward.get_state().matrix;
+        assert_relative_eq!(diff.norm(), 0.0, epsilon = 1e-12);
+        assert_eq!(
+            forward.gate_unitary("CZ", &[0, 2], &[]).unwrap(),
+            build_cz_unitary(0, 2, 3)
+        );
+        assert!(forward.gate_unitary("CZ", &[1, 1], &[]).is_err());
+    }
+
+    #[test]
+    fn test_expectation_value_checked_rejects_non_hermitian() {
+        let mut sim = QuantumSimulator::new(1);
+        sim.apply_gate("Hadamard", &[0], &[]).unwrap();
+        assert_relative_eq!(sim.expectation_value_checked(&pauli_x()).unwrap(), 1.0, epsilon = 1e-12);
+
+        // σ⁺ = |0⟩⟨1| is not Hermitian, yet the unchecked path returns a plausible 0.5
+        let mut raising = DMatrix::zeros(2, 2);
+        raising[(0, 1)] = Complex::new(1.0, 0.0);
+        assert_relative_eq!(sim.expectation_value(&raising), 0.5, epsilon = 1e-12);
+        assert_eq!(sim.expectation_value_checked(&raising), Err(QsimError::NotHermitian));
+
+        let mut y_like = pauli_y();
+        y_like[(1, 0)] = Complex::new(0.0, -0.5);
+        assert_eq!(sim.expectation_value_checked(&y_like), Err(QsimError::NotHermitian));
+        assert!(matches!(
+            sim.expectation_value_checked(&DMatrix::identity(4, 4)),
+            Err(QsimError::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn test_u3_requires_exactly_three_params() {
+        let pi = std::f64::consts::PI;
+        let mut sim = QuantumSimulator::new(1);
+        sim.apply_gate("U3", &[0], &[pi, 0.0, pi]).unwrap();
+        assert_relative_eq!(sim.get_state().probabilities()[1], 1.0, epsilon = 1e-12);
+        assert!(matches!(sim.apply_gate("U3", &[0], &[pi, 0.0]), Err(QsimError::InvalidParameter(_))));
+        assert!(matches!(
+            sim.apply_gate("U3", &[0], &[pi, 0.0, pi, 1.0]),
+            Err(QsimError::InvalidParameter(msg)) if msg.contains("exactly 3")
+        ));
+    }
+
+    #[test]
+    fn test_controlled_matrix_fires_only_on_control_one() {
+        let mut idle = QuantumSimulator::new(2);
+        idle.apply_controlled_matrix(&hadamard(), &[0], &[1]).unwrap();
+        assert_relative_eq!(idle.get_state().probabilities()[0], 1.0, epsilon = 1e-12);
+
+        let mut fired = QuantumSimulator::new(2);
+        fired.apply_gate("PauliX", &[0], &[]).unwrap();
+        fired.apply_controlled_matrix(&hadamard(), &[0], &[1]).unwrap();
+        let probs = fired.get_state().probabilities();
+        assert_relative_eq!(probs[2], 0.5, epsilon = 1e-12);
+        assert_relative_eq!(probs[3], 0.5, epsilon = 1e-12);
+        assert_relative_eq!(fired.expectation_pauli("ZX").unwrap(), -1.0, epsilon = 1e-12);
+
+        // Controlled-X on a control below the target matches CNOT
+        let mut sim = QuantumSimulator::new(3);
+        sim.apply_gate("Hadamard", &[2], &[]).unwrap();
+        sim.apply_controlled_matrix(&pauli_x(), &[2], &[0]).unwrap();
+        assert_relative_eq!(sim.expectation_pauli("ZIZ").unwrap(), 1.0, epsilon = 1e-12);
+
+        // Two controls and a two-wire target: controlled-controlled-SWAP
+        let mut swap = QuantumSimulator::new(4);
+        for w in [0, 1, 2] {
+            swap.apply_gate("PauliX", &[w], &[]).unwrap();
+        }
+        swap.apply_controlled_matrix(&build_swap_unitary(0, 1, 2), &[0, 1], &[2, 3]).unwrap();
+        assert_relative_eq!(swap.get_state().probabilities()[0b1101], 1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_controlled_matrix_validation() {
+        let mut sim = QuantumSimulator::new(3);
+        assert!(matches!(
+            sim.apply_controlled_matrix(&hadamard(), &[0], &[1, 2]),
+            Err(QsimError::InvalidParameter(_))
+        ));
+        assert_eq!(
+            sim.apply_controlled_matrix(&hadamard(), &[3], &[1]),
+            Err(QsimError::WireOutOfRange(3))
+        );
+        assert!(sim.apply_controlled_matrix(&hadamard(), &[1], &[1]).is_err());
+        let not_unitary = hadamard() * Complex::new(2.0, 0.0);
+        assert_eq!(
+            sim.apply_controlled_matrix(&not_unitary, &[0], &[1]),
+            Err(QsimError::NotUnitary)
+        );
+    }
+
+    #[test]
+    fn test_cphase_pi_equals_cz() {
+        let sim = QuantumSimulator::new(3);
+        let pi = std::f64::consts::PI;
+        for wires in [[0, 1], [2, 0]] {
+            let cz = sim.gate_unitary("CZ", &wires, &[]).unwrap();
+            for name in ["CPhase", "CP"] {
+                let cphase = sim.gate_unitary(name, &wires, &[pi]).unwrap();
+                assert_relative_eq!((&cphase - &cz).norm(), 0.0, epsilon = 1e-12);
+            }
+        }
+        let cp = sim.gate_unitary("CP", &[1, 2], &[0.3]).unwrap();
+        assert_relative_eq!((cp - build_cphase_unitary(1, 2, 0.3, 3)).norm(), 0.0, epsilon = 1e-12);
+        assert!(sim.gate_unitary("CP", &[1, 2], &[]).is_err());
+    }
+
+    #[test]
+    fn test_cy_and_ch_use_controlled_builder() {
+        let sim = QuantumSimulator::new(3);
+        for (name, gate) in [("CY", pauli_y()), ("CH", hadamard())] {
+            let built = sim.gate_unitary(name, &[2, 0], &[]).unwrap();
+            let expected = build_controlled_unitary(2, 0, &gate, 3);
+            assert_relative_eq!((built - expected).norm(), 0.0, epsilon = 1e-12);
+            assert!(sim.gate_unitary(name, &[1], &[]).is_err());
+        }
+
+        // CH fires only when the control is |1⟩
+        let mut sim = QuantumSimulator::new(2);
+        sim.apply_gate("CH", &[0, 1], &[]).unwrap();
+        assert_relative_eq!(sim.get_state().probabilities()[0], 1.0, epsilon = 1e-12);
+        sim.apply_gate("PauliX", &[0], &[]).unwrap();
+        sim.apply_gate("CH", &[0, 1], &[]).unwrap();
+        assert_relative_eq!(sim.expectation_pauli("ZX").unwrap(), -1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_free_evolve_is_independent_of_step_count() {
+        let (t1, t2) = (50.0, 70.0);
+        let evolve = |total_time: f64, n_steps: usize| {
+            let mut sim = QuantumSimulator::new(2);
+            sim.apply_gate("RY", &[1], &[2.0]).unwrap();
+            sim.free_evolve(1, total_time, t1, t2, n_steps).unwrap();
+            sim
+        };
+
+        let single = evolve(2.0, 1);
+        let fine = evolve(2.0, 10);
+        let diff = &single.get_state().matrix - &fine.get_state().matrix;
+        assert_relative_eq!(diff.norm(), 0.0, epsilon = 1e-12);
+
+        // Excited population decays as e^{-t/T1} towards the zero-temperature value 0
+        let initial = (1.0_f64).sin().powi(2);
+        for total_time in [10.0, 100.0, 1000.0] {
+            let excited = evolve(total_time, 25).get_state().probabilities()[1];
+            assert_relative_eq!(excited, initial * (-total_time / t1).exp(), epsilon = 1e-12);
+        }
+        assert!(evolve(1000.0, 25).get_state().probabilities()[1] < 1e-8);
+
+        let mut sim = QuantumSimulator::new(1);
+        assert_eq!(sim.free_evolve(1, 1.0, t1, t2, 4), Err(QsimError::WireOutOfRange(1)));
+        assert!(sim.free_evolve(0, 1.0, t1, t2, 0).is_err());
+    }
+
+    #[test]
+    fn test_ising_gates_reject_repeated_wire() {
+        let mut sim = QuantumSimulator::new(2);
+        for name in ["RXX", "RYY", "RZZ"] {
+            assert!(matches!(
+                sim.apply_gate(name, &[0, 0], &[0.3]),
+                Err(QsimError::InvalidParameter(_))
+            ));
+        }
+        assert_relative_eq!(sim.get_state().probabilities()[0], 1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_xyz_interaction_rejects_repeated_wire() {
+        let mut sim = QuantumSimulator::new(2);
+        for name in ["XYZ", "Canonical"] {
+            assert!(matches!(
+                sim.apply_gate(name, &[1, 1], &[0.1, 0.2, 0.3]),
+                Err(QsimError::InvalidParameter(_))
+            ));
+        }
+        assert!(sim.gate_unitary("XYZ", &[0, 0], &[0.1, 0.2, 0.3]).is_err());
+    }
+
+    #[test]
+    fn test_cphase_fraction_rejects_repeated_wire() {
+        let mut sim = QuantumSimulator::new(2);
+        assert!(matches!(
+            sim.apply_gate("CPhaseFraction", &[1, 1], &[2.0]),
+            Err(QsimError::InvalidParameter(msg)) if msg.contains("must differ")
+        ));
+
+        // A duplicated wire handed to the QFT builder now surfaces as an error
+        let qft = Circuit::qft(&[0, 1, 0]);
+        assert!(QuantumSimulator::new(2).run_circuit(&qft).is_err());
+    }
+
+    #[test]
+    fn test_diff_report_rejects_register_mismatch() {
+        let small = QuantumSimulator::new(1);
+        let large = QuantumSimulator::new(2);
+        assert!(matches!(small.diff_report(&large), Err(QsimError::InvalidParameter(_))));
+        assert_eq!(large.diff_report(&large), Ok((0, 0.0)));
+    }
+
+    #[test]
+    fn test_apply_noise_is_one_relaxation_channel() {
+        for (protected, gamma, lambda) in [(false, 0.05_f64, 0.02), (true, 0.01, 0.004)] {
+            let mut sim = QuantumSimulator::new(2);
+            sim.apply_gate("Hadamard", &[1], &[]).unwrap();
+            sim.apply_gate("PauliX", &[0], &[]).unwrap();
+            sim.apply_noise(1, protected);
+            sim.apply_noise(0, protected);
+
+            let rho = &sim.get_state().matrix;
+            // Wire 1 in |+⟩: coherence shrinks by √(1-γ)(1-2λ), |1⟩ population by (1-γ)
+            let coherence = 0.5 * (1.0 - gamma).sqrt() * (1.0 - 2.0 * lambda);
+            let p1 = 0.5 * (1.0 - gamma);
+            assert_relative_eq!(rho[(2, 3)].re, (1.0 - gamma) * coherence, epsilon = 1e-12);
+            assert_relative_eq!(rho[(3, 3)].re, (1.0 - gamma) * p1, epsilon = 1e-12);
+            assert_relative_eq!(sim.get_state().trace().re, 1.0, epsilon = 1e-12);
+            assert!(sim.assert_physical(1e-10).is_ok());
+        }
+
+        // Out-of-range wires are ignored
+        let mut sim = QuantumSimulator::new(1);
+        sim.apply_noise(3, false);
+        assert_relative_eq!(sim.get_state().probabilities()[0], 1.0, epsilon = 1e-12);
+    }
 }
@@ -0,0 +1,190 @@
+use nalgebra::{DMatrix, DVector};
+use num_complex::Complex;
+use crate::error::QsimError;
+use crate::gates::single_qubit_gate;
+use rand::prelude::*;
+
+/// Pure-state quantum-jump simulator: one stochastic trajectory of a noisy evolution
+///
+/// Noise is unravelled by sampling one Kraus operator per channel application, so memory
+/// grows as 2^n instead of 4^n. Averaging |ψ⟩⟨ψ| over many trajectories reproduces the
+/// density matrix of `QuantumSimulator`.
+pub struct TrajectorySimulator {
+    state: DVector<Complex<f64>>,
+    num_qubits: usize,
+    rng: StdRng,
+}
+
+impl TrajectorySimulator {
+    /// Create a new trajectory in the |0...0⟩ state
+    pub fn new(num_qubits: usize) -> Self {
+        let mut state = DVector::zeros(1 << num_qubits);
+        state[0] = Complex::new(1.0, 0.0);
+        TrajectorySimulator {
+            state,
+            num_qubits,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Seed the random number generator used for jump sampling
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Reset to |0...0⟩ without touching the random number generator
+    pub fn reset(&mut self) {
+        self.state.fill(Complex::new(0.0, 0.0));
+        self.state[0] = Complex::new(1.0, 0.0);
+    }
+
+    /// Current normalized state vector
+    pub fn state(&self) -> &DVector<Complex<f64>> {
+        &self.state
+    }
+
+    /// Apply a named single-qubit gate (as accepted by `QuantumSimulator::apply_gate`)
+    pub fn apply_gate(&mut self, gate_name: &str, wire: usize, params: &[f64]) -> Result<(), QsimError> {
+        self.check_wire(wire)?;
+        let gate = single_qubit_gate(gate_name, params)
+            .ok_or_else(|| QsimError::UnknownGate(gate_name.to_string()))?;
+        self.state = self.apply_local(&gate, wire);
+        Ok(())
+    }
+
+    /// Apply a single-qubit channel to `wire` by sampling one of its Kraus operators
+    ///
+    /// Operator Kᵢ is chosen with probability ‖Kᵢ|ψ⟩‖² and the state becomes Kᵢ|ψ⟩
+    /// renormalized. Returns i, so a sequence of calls records the quantum-jump history.
+    pub fn step_with_log(
+        &mut self,
+        kraus: &[DMatrix<Complex<f64>>],
+        wire: usize,
+    ) -> Result<usize, QsimError> {
+        self.check_wire(wire)?;
+        if kraus.is_empty() {
+            return Err(QsimError::InvalidParameter("Channel has no Kraus operators".to_string()));
+        }
+        if let Some(k) = kraus.iter().find(|k| k.shape() != (2, 2)) {
+            return Err(QsimError::InvalidParameter(format!(
+                "Kraus operators must be 2x2, got {}x{}",
+                k.nrows(),
+                k.ncols()
+            )));
+        }
+
+        let branches: Vec<DVector<Complex<f64>>> =
+            kraus.iter().map(|k| self.apply_local(k, wire)).collect();
+        let weights: Vec<f64> = branches.iter().map(|b| b.norm_squared()).collect();
+        let total: f64 = weights.iter().sum();
+        if total < 1e-12 {
+            return Err(QsimError::InvalidParameter(format!(
+                "Kraus operators annihilate the state (total weight {})",
+                total
+            )));
+        }
+
+        // Round-off can leave the threshold past the last bin; fall back to a branch that
+        // can actually occur rather than one with zero weight
+        let mut threshold = self.rng.gen::<f64>() * total;
+        let mut chosen = weights.iter().rposition(|&w| w > 0.0).unwrap_or(0);
+        for (i, &w) in weights.iter().enumerate() {
+            if w > 0.0 && threshold < w {
+                chosen = i;
+                break;
+            }
+            threshold -= w;
+        }
+
+        let norm = weights[chosen].sqrt();
+        self.state = &branches[chosen] / Complex::new(norm, 0.0);
+        Ok(chosen)
+    }
+
+    /// 2x2 operator acting on one wire of the state vector (wire 0 is the most significant bit)
+    fn apply_local(&self, op: &DMatrix<Complex<f64>>, wire: usize) -> DVector<Complex<f64>> {
+        let mask = 1 << (self.num_qubits - 1 - wire);
+        let mut out = self.state.clone();
+        for i in (0..self.state.len()).filter(|i| i & mask == 0) {
+            let (a0, a1) = (self.state[i], self.state[i | mask]);
+            out[i] = op[(0, 0)] * a0 + op[(0, 1)] * a1;
+            out[i | mask] = op[(1, 0)] * a0 + op[(1, 1)] * a1;
+        }
+        out
+    }
+
+    fn check_wire(&self, wire: usize) -> Result<(), QsimError> {
+        if wire >= self.num_qubits {
+            return Err(QsimError::WireOutOfRange(wire));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise_model::amplitude_damping_kraus;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_jump_frequency_matches_gamma() {
+        let gamma = 0.3;
+        let kraus = amplitude_damping_kraus(gamma);
+        let mut traj = TrajectorySimulator::new(2);
+        traj.set_seed(23);
+
+        let n_trajectories = 20_000;
+        let mut jumps = 0;
+        for _ in 0..n_trajectories {
+            traj.reset();
+            traj.apply_gate("PauliX", 1, &[]).unwrap();
+            let index = traj.step_with_log(&kraus, 1).unwrap();
+            assert_relative_eq!(traj.state().norm(), 1.0, epsilon = 1e-12);
+            if index == 1 {
+                jumps += 1;
+                // K1 = √γ |0⟩⟨1| sends the excited wire back to |00⟩
+                assert_relative_eq!(traj.state()[0].norm(), 1.0, epsilon = 1e-12);
+            }
+        }
+
+        let frequency = jumps as f64 / n_trajectories as f64;
+        assert!((frequency - gamma).abs() < 0.015, "jump frequency {}", frequency);
+        assert!(traj.step_with_log(&kraus, 2).is_err());
+    }
+
+    #[test]
+    fn test_step_rejects_bad_channels() {
+        let mut traj = TrajectorySimulator::new(2);
+        traj.set_seed(5);
+
+        let wide = vec![DMatrix::<Complex<f64>>::identity(4, 4)];
+        assert!(matches!(traj.step_with_log(&wide, 0), Err(QsimError::InvalidParameter(_))));
+
+        // |0⟩⟨1| applied to |00⟩ leaves nothing to renormalize
+        let mut lower = DMatrix::zeros(2, 2);
+        lower[(0, 1)] = Complex::new(1.0, 0.0);
+        assert!(matches!(traj.step_with_log(&[lower], 0), Err(QsimError::InvalidParameter(_))));
+        assert_relative_eq!(traj.state()[0].norm(), 1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_zero_weight_branch_is_never_chosen() {
+        // On |00⟩ only K0 of amplitude damping has weight; the jump branch K1 must never fire
+        let kraus = amplitude_damping_kraus(0.4);
+        let mut traj = TrajectorySimulator::new(1);
+        traj.set_seed(11);
+        for _ in 0..1000 {
+            traj.reset();
+            assert_eq!(traj.step_with_log(&kraus, 0).unwrap(), 0);
+        }
+
+        let mut trailing = kraus.clone();
+        trailing.push(DMatrix::zeros(2, 2));
+        for _ in 0..1000 {
+            traj.reset();
+            traj.apply_gate("PauliX", 0, &[]).unwrap();
+            assert!(traj.step_with_log(&trailing, 0).unwrap() < 2);
+        }
+    }
+}
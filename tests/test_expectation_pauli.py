@@ -0,0 +1,60 @@
+"""
+Test Pauli-string expectation values through the Python bindings.
+Verifies expectation_pauli on a Bell state and its argument validation.
+"""
+
+import quantum_core
+
+
+def _bell_state():
+    """H on wire 0 followed by CNOT: (|00> + |11>)/sqrt(2)"""
+    sim = quantum_core.QuantumSimulator(3)
+    sim.apply_gate("Hadamard", [0], [])
+    sim.apply_gate("CNOT", [0, 1], [])
+    return sim
+
+
+def test_bell_correlators():
+    """ZZ and XX are +1 on the Bell pair, single-wire Z averages to 0"""
+    print("\n[Test 1] Bell state Pauli expectations")
+
+    sim = _bell_state()
+    cases = [("ZZ", [0, 1], 1.0), ("XX", [0, 1], 1.0), ("YY", [1, 0], -1.0),
+             ("Z", [0], 0.0), ("Z", [2], 1.0), ("ZIZ", [0, 2, 1], 1.0)]
+    for pauli, wires, expected in cases:
+        value = sim.expectation_pauli(pauli, wires)
+        print(f"<{pauli}> on {wires} = {value:.4f} (expected: {expected:.4f})")
+        assert abs(value - expected) < 1e-10
+    print("✅ PASS")
+
+
+def test_length_mismatch_raises():
+    """A string that does not match the wire list is rejected"""
+    print("\n[Test 2] Length mismatch raises ValueError")
+
+    sim = _bell_state()
+    for pauli, wires in [("ZZ", [0]), ("Z", [0, 1]), ("Q", [0]), ("Z", [3])]:
+        try:
+            sim.expectation_pauli(pauli, wires)
+        except ValueError as err:
+            print(f"{pauli} on {wires}: {err}")
+        else:
+            raise AssertionError(f"{pauli} on {wires} should raise")
+    print("✅ PASS")
+
+
+def main():
+    print("="*60)
+    print("Testing Pauli Expectation Values")
+    print("="*60)
+
+    test_bell_correlators()
+    test_length_mismatch_raises()
+
+    print("\n" + "="*60)
+    print("All tests passed!")
+    print("="*60)
+
+
+if __name__ == "__main__":
+    main()